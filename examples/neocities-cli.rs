@@ -1,17 +1,17 @@
-use std::{fs::File, io::Read, path::PathBuf};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 use clap::{Parser, Subcommand};
-use neocities::{ListEntry, Neocities};
-use walkdir::WalkDir;
+use neocities::{IndexHtml, ListEntry, Neocities, NeocitiesError, UploadDirOptions};
 
 #[tokio::main]
 async fn main() {
-    match run().await {
-        Err(e) => {
-            println!("error: {}", e);
-            std::process::exit(1);
-        }
-        _ => {}
+    if let Err(e) = run().await {
+        println!("error: {}", e);
+        std::process::exit(1);
     }
 }
 
@@ -40,7 +40,8 @@ enum ApiCmd {
     List { directory: Option<String> },
     /// Gets the API key for your site or generates it if one does not exist
     Key,
-    /// Delete a list of files or directories from your site
+    /// Delete a list of files or directories from your site.
+    /// Pass `-` to read newline-separated paths from stdin
     Delete { paths: Vec<String> },
     /// Delete ALL FILES recursively from your Neocities site.
     /// NOTE: This will not delete index.html, as that file is required
@@ -55,6 +56,15 @@ enum ApiCmd {
         #[clap(short, long)]
         custom_path: Option<String>,
     },
+    /// Upload a list of local files in a single request, each to its path relative to the
+    /// root directory on the site. Pass `-` to read newline-separated paths from stdin
+    UploadMany {
+        paths: Vec<String>,
+        /// The local directory treated as the root of the site, every file must be inside it.
+        /// Defaults to the current directory
+        #[clap(short, long)]
+        root: Option<PathBuf>,
+    },
     /// This command uploads all files recursively within a specified directory
     /// The specified directory will be treated as the root
     UploadAll { root: PathBuf },
@@ -68,7 +78,16 @@ async fn run() -> Result<(), String> {
     } else if let Some(key) = cli.key {
         Neocities::new(key)
     } else {
-        return Err("No login specified!".into());
+        match Neocities::from_env() {
+            Ok(api) => api,
+            Err(NeocitiesError::MissingCredentials(vars)) => {
+                return Err(format!(
+                    "No login specified! Pass --key or --username and --password, or set {}",
+                    vars.join(", ")
+                ))
+            }
+            Err(e) => return Err(e.to_string()),
+        }
     };
 
     match cli.subcommand {
@@ -95,6 +114,10 @@ async fn run() -> Result<(), String> {
                 .await
                 .map_err(|e| e.to_string())?;
 
+            if files.is_empty() {
+                println!("No files found");
+            }
+
             for entry in files {
                 match entry {
                     ListEntry::File {
@@ -108,7 +131,10 @@ async fn run() -> Result<(), String> {
                         println!("Updated at: {}", updated_at);
                         println!("SHA-1: {}", sha1_hash);
                     }
-                    _ => {}
+                    ListEntry::Directory { path, updated_at } => {
+                        println!("Directory: {}", path);
+                        println!("Updated at: {}", updated_at);
+                    }
                 }
             }
         }
@@ -117,48 +143,26 @@ async fn run() -> Result<(), String> {
             println!("Neocities Key: {}", key);
         }
         ApiCmd::Delete { paths } => {
-            let res = api.delete(paths).await.map_err(|e| e.to_string())?;
+            let res = api
+                .delete(read_paths(paths)?)
+                .await
+                .map_err(|e| e.to_string())?;
             println!("{}", res);
         }
         ApiCmd::DeleteAll => {
-            let list = api.list("").await.map_err(|e| e.to_string())?;
-
-            for entry in &list {
-                match entry {
-                    ListEntry::File {
-                        path,
-                        size: _,
-                        updated_at: _,
-                        sha1_hash: _,
-                    } => {
-                        if path == "index.html" {
-                            continue;
-                        }
-
-                        let res = api.delete([path.clone()]).await;
-
-                        if res.is_err() {
-                            println!("Failed to delete `{}`", path);
-                        }
-                    }
-                    _ => {}
-                }
-            }
+            let report = api
+                .delete_all(IndexHtml::Keep)
+                .await
+                .map_err(|e| e.to_string())?;
 
-            for entry in &list {
-                match entry {
-                    ListEntry::Directory {
-                        path,
-                        updated_at: _,
-                    } => {
-                        let res = api.delete([path.clone()]).await;
+            println!(
+                "Deleted {} files and {} directories",
+                report.deleted_files.len(),
+                report.deleted_dirs.len()
+            );
 
-                        if res.is_err() {
-                            //println!("Failed to delete `{}`", path);
-                        }
-                    }
-                    _ => {}
-                }
+            for (path, e) in report.failed_files.iter().chain(&report.failed_dirs) {
+                println!("Failed to delete {}: {}", path, e);
             }
         }
         ApiCmd::Upload {
@@ -185,33 +189,71 @@ async fn run() -> Result<(), String> {
                 .await
                 .map_err(|e| e.to_string())?;
         }
-        ApiCmd::UploadAll { root } => {
-            for entry in WalkDir::new(&root) {
-                let entry = entry.map_err(|e| e.to_string())?;
-                let path = entry.path();
-                let neocities_path = path
-                    .strip_prefix(&root)
-                    .map_err(|e| e.to_string())?
-                    .to_string_lossy()
-                    .to_string();
-                let neocities_path = neocities_path.replace("\\", "/");
-
-                if path.is_dir() {
-                    continue;
-                }
-
-                let mut file_vec = Vec::new();
-                File::open(&path)
-                    .map_err(|e| e.to_string())?
-                    .read_to_end(&mut file_vec)
-                    .map_err(|e| e.to_string())?;
+        ApiCmd::UploadMany { paths, root } => {
+            let root = root.unwrap_or_else(|| PathBuf::from("."));
+            let root = root
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", root.display(), e))?;
 
-                api.upload(neocities_path, file_vec)
-                    .await
-                    .map_err(|e| e.to_string())?;
+            let mut files = Vec::new();
+            for path in read_paths(paths)? {
+                let file = std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?;
+                files.push((site_path(&root, &path)?, file));
             }
+
+            let res = api.upload_many(files).await.map_err(|e| e.to_string())?;
+            println!("{}", res);
+        }
+        ApiCmd::UploadAll { root } => {
+            api.upload_dir(&root, &UploadDirOptions::new())
+                .await
+                .map_err(|e| e.to_string())?;
         }
     }
 
     Ok(())
 }
+
+// The path on the site of the local file at `path`, relative to the local site root `root`
+fn site_path(root: &Path, path: &str) -> Result<String, String> {
+    let absolute = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let relative = absolute
+        .strip_prefix(root)
+        .map_err(|_| format!("{} is not inside {}", path, root.display()))?;
+
+    let segments: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    Ok(segments.join("/"))
+}
+
+// Use the paths given as arguments, or read them from stdin one per line if the only one is `-`
+fn read_paths(paths: Vec<String>) -> Result<Vec<String>, String> {
+    let paths = if paths == ["-"] {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())?;
+
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        paths
+    };
+
+    if paths.is_empty() {
+        return Err(
+            "No paths given, pass them as arguments or `-` to read them from stdin".to_string(),
+        );
+    }
+
+    Ok(paths)
+}
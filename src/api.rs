@@ -0,0 +1,71 @@
+use std::future::Future;
+
+use crate::{Info, ListEntry, Neocities, NeocitiesError};
+
+/// The core Neocities API calls as a trait, so code can be written against
+/// either a real [`Neocities`] client or a fake one in tests.
+///
+/// Enable the `test-util` feature for `FakeNeocities`, an in-memory implementation.
+///
+/// Only the calls on this trait are covered. The higher level helpers of [`Neocities`], such as
+/// [`Neocities::upload_dir`], [`Neocities::deploy_files`], [`Neocities::apply`] and
+/// [`Neocities::delete_all`], only exist on the real client and can't run against a fake.
+/// To unit test your own deploy logic, write it on top of these calls instead
+pub trait NeocitiesApi {
+    /// See [`Neocities::list`]
+    fn list(
+        &self,
+        path: &str,
+    ) -> impl Future<Output = Result<Vec<ListEntry>, NeocitiesError>> + Send;
+
+    /// See [`Neocities::info`]
+    fn info(&self, site_name: &str) -> impl Future<Output = Result<Info, NeocitiesError>> + Send;
+
+    /// See [`Neocities::key`]
+    fn key(&self) -> impl Future<Output = Result<String, NeocitiesError>> + Send;
+
+    /// See [`Neocities::upload`]
+    fn upload(
+        &self,
+        file_path: String,
+        file: Vec<u8>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send;
+
+    /// See [`Neocities::delete`]
+    fn delete(
+        &self,
+        file_paths: Vec<String>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send;
+}
+
+impl NeocitiesApi for Neocities {
+    fn list(
+        &self,
+        path: &str,
+    ) -> impl Future<Output = Result<Vec<ListEntry>, NeocitiesError>> + Send {
+        Neocities::list(self, path)
+    }
+
+    fn info(&self, site_name: &str) -> impl Future<Output = Result<Info, NeocitiesError>> + Send {
+        Neocities::info(self, site_name)
+    }
+
+    fn key(&self) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        Neocities::key(self)
+    }
+
+    fn upload(
+        &self,
+        file_path: String,
+        file: Vec<u8>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        Neocities::upload(self, file_path, file)
+    }
+
+    fn delete(
+        &self,
+        file_paths: Vec<String>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        Neocities::delete(self, file_paths)
+    }
+}
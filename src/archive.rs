@@ -0,0 +1,257 @@
+#[cfg(feature = "archive")]
+use std::fs::File;
+#[cfg(feature = "zip")]
+use std::io::{Read, Seek, Write};
+use std::{
+    collections::HashSet,
+    path::{Component, Path},
+};
+
+#[cfg(feature = "archive")]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+#[cfg(feature = "archive")]
+use tar::{Archive, Builder, EntryType, Header};
+#[cfg(feature = "zip")]
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{sync::UPLOAD_BATCH_SIZE, ListEntry, Neocities, NeocitiesError};
+
+/// The result of backing up a site into an archive.
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    /// Paths of files written to the archive
+    pub exported: Vec<String>,
+    /// Paths of files that could not be downloaded, with the reason
+    pub failed: Vec<(String, NeocitiesError)>,
+}
+
+/// The result of restoring a site from an archive.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Paths of files uploaded from the archive
+    pub restored: Vec<String>,
+    /// Paths of files deleted from the site because they were not in the archive
+    pub pruned: Vec<String>,
+    /// Names of archive entries that were skipped because they point outside the site, such as
+    /// `../file`, or aren't regular files, such as symbolic links
+    pub rejected: Vec<String>,
+}
+
+#[cfg(feature = "archive")]
+impl Neocities {
+    /// Back up every file on the current [`Neocities`] site into a gzipped tar archive
+    /// created at `dest`, keeping the directory structure of the site and the time each file
+    /// was last updated.
+    ///
+    /// Like [`Neocities::export_zip`], files are downloaded and written one at a time, and
+    /// files that fail to download are listed in [`ExportReport::failed`] instead of stopping the backup
+    pub async fn backup_to_archive<P: AsRef<Path>>(
+        &self,
+        dest: P,
+    ) -> Result<ExportReport, NeocitiesError> {
+        let gzip = GzEncoder::new(File::create(dest)?, Compression::default());
+        let mut tar = Builder::new(gzip);
+        let mut report = ExportReport::default();
+
+        for entry in self.list("").await? {
+            let mut header = Header::new_gnu();
+            header.set_mtime(
+                entry
+                    .updated_at_utc()
+                    .map_or(0, |time| time.timestamp().max(0) as u64),
+            );
+
+            match entry {
+                ListEntry::Directory { path, .. } => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    tar.append_data(&mut header, format!("{}/", path), std::io::empty())?;
+                }
+                ListEntry::File { path, .. } => match self.download(&path).await {
+                    Ok(file) => {
+                        header.set_mode(0o644);
+                        header.set_size(file.len() as u64);
+                        tar.append_data(&mut header, &path, file.as_slice())?;
+                        report.exported.push(path);
+                    }
+                    Err(e) => report.failed.push((path, e)),
+                },
+            }
+        }
+
+        tar.into_inner()?.finish()?;
+        Ok(report)
+    }
+
+    /// Restore the current [`Neocities`] site from the archive at `archive`, such as one made by
+    /// [`Neocities::backup_to_archive`], uploading each file in it to the same path on the site.
+    /// Archives ending in `.zip` are read with [`Neocities::import_zip`] when the `zip` feature
+    /// is enabled, anything else is read as a gzipped tar archive.
+    ///
+    /// Every file in a tar archive is read into memory before uploading starts. Entries with
+    /// unsafe paths like `../file` and entries that aren't regular files, such as symbolic or
+    /// hard links, are skipped and listed in [`ImportReport::rejected`].
+    /// If `prune` is set, files on the site that are not in the archive are deleted afterwards,
+    /// except for `index.html` which can't be deleted
+    pub async fn restore_from_archive<P: AsRef<Path>>(
+        &self,
+        archive: P,
+        prune: bool,
+    ) -> Result<ImportReport, NeocitiesError> {
+        let archive = archive.as_ref();
+
+        #[cfg(feature = "zip")]
+        if archive
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        {
+            return self.import_zip(File::open(archive)?, prune).await;
+        }
+
+        let mut report = ImportReport::default();
+        let mut files = Vec::new();
+        let mut tar = Archive::new(GzDecoder::new(File::open(archive)?));
+
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_dir() {
+                continue;
+            }
+
+            let name = entry.path()?.to_string_lossy().to_string();
+            let path = match site_path(Path::new(&name)) {
+                Some(path) if entry_type.is_file() => path,
+                _ => {
+                    report.rejected.push(name);
+                    continue;
+                }
+            };
+
+            let mut contents = Vec::new();
+            std::io::Read::read_to_end(&mut entry, &mut contents)?;
+            files.push((path, contents));
+        }
+
+        let mut files = files.into_iter().peekable();
+        while files.peek().is_some() {
+            let batch: Vec<_> = files.by_ref().take(UPLOAD_BATCH_SIZE).collect();
+            let paths: Vec<String> = batch.iter().map(|(path, _)| path.clone()).collect();
+
+            self.upload_many(batch).await?;
+            report.restored.extend(paths);
+        }
+
+        if prune {
+            let keep: HashSet<&str> = report.restored.iter().map(String::as_str).collect();
+            report.pruned = self.prune(&keep).await?;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl Neocities {
+    /// Back up every file on the current [`Neocities`] site into a zip archive written to `writer`,
+    /// keeping the directory structure of the site.
+    ///
+    /// Files are downloaded with [`Neocities::download`] and written to the archive one at a time,
+    /// so only one file is held in memory at once. Files that fail to download are left out of
+    /// the archive and listed in [`ExportReport::failed`] instead of stopping the export
+    pub async fn export_zip<W: Write + Seek>(
+        &self,
+        writer: W,
+    ) -> Result<ExportReport, NeocitiesError> {
+        let mut zip = ZipWriter::new(writer);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut report = ExportReport::default();
+
+        for entry in self.list("").await? {
+            match entry {
+                ListEntry::Directory { path, .. } => zip.add_directory(path, options)?,
+                ListEntry::File { path, .. } => match self.download(&path).await {
+                    Ok(file) => {
+                        zip.start_file(path.clone(), options)?;
+                        zip.write_all(&file)?;
+                        report.exported.push(path);
+                    }
+                    Err(e) => report.failed.push((path, e)),
+                },
+            }
+        }
+
+        zip.finish()?;
+        Ok(report)
+    }
+
+    /// Restore the current [`Neocities`] site from a zip archive, such as one made by
+    /// [`Neocities::export_zip`], uploading each file in it to the same path on the site.
+    ///
+    /// Directory entries are skipped since directories are created by the files in them, and
+    /// entries with unsafe paths like `../file` are skipped and listed in [`ImportReport::rejected`].
+    /// If `prune` is set, files on the site that are not in the archive are deleted afterwards,
+    /// except for `index.html` which can't be deleted
+    pub async fn import_zip<R: Read + Seek>(
+        &self,
+        reader: R,
+        prune: bool,
+    ) -> Result<ImportReport, NeocitiesError> {
+        let mut zip = ZipArchive::new(reader)?;
+        let mut report = ImportReport::default();
+        let mut files = Vec::new();
+
+        for index in 0..zip.len() {
+            let file = zip.by_index(index)?;
+
+            if file.is_dir() {
+                continue;
+            }
+
+            match file.enclosed_name().and_then(site_path) {
+                Some(path) => files.push((index, path)),
+                None => report.rejected.push(file.name().to_string()),
+            }
+        }
+
+        for batch in files.chunks(UPLOAD_BATCH_SIZE) {
+            let mut upload = Vec::new();
+
+            for (index, path) in batch {
+                let mut contents = Vec::new();
+                zip.by_index(*index)?.read_to_end(&mut contents)?;
+                upload.push((path.clone(), contents));
+            }
+
+            self.upload_many(upload).await?;
+            report
+                .restored
+                .extend(batch.iter().map(|(_, path)| path.clone()));
+        }
+
+        if prune {
+            let keep: HashSet<&str> = report.restored.iter().map(String::as_str).collect();
+            report.pruned = self.prune(&keep).await?;
+        }
+
+        Ok(report)
+    }
+}
+
+// The path on the site of an archive entry at `path`, or `None` if it would point outside the
+// site. `.` components are dropped, so `./images/cat.png` is restored to `images/cat.png`
+fn site_path(path: &Path) -> Option<String> {
+    let mut segments = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
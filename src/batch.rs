@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::{ListEntry, Neocities, NeocitiesError};
+
+/// A queue of uploads and deletions that are undone on failure where possible.
+///
+/// **A batch is not atomic.** Operations are sent one at a time in the order they were
+/// queued, so anyone viewing the site can see it partway through a batch. If an operation
+/// fails, [`Batch::execute`] makes a best-effort attempt to undo the operations that already
+/// completed, newest first:
+///
+/// - Deleted files are re-uploaded from copies downloaded right before deleting them
+/// - Overwritten files are re-uploaded with their previous contents
+/// - Newly uploaded files are deleted
+///
+/// Undoing an operation can fail too, and directories created as a side effect of an upload are
+/// left behind. Deleted directories with no files in them can't be restored. Anything that could
+/// not be undone is listed in `not_rolled_back` of [`BatchError::Operation`].
+///
+/// Since copies of anything that will be overwritten or deleted are downloaded before each
+/// operation, batches touching large files or directories cost extra time and bandwidth
+#[derive(Default)]
+pub struct Batch {
+    ops: Vec<Op>,
+}
+
+enum Op {
+    Upload { path: String, data: Vec<u8> },
+    Delete { path: String },
+}
+
+// An operation that has been applied, along with the files it replaced
+struct Completed {
+    path: String,
+    upload: bool,
+    previous: Vec<(String, Vec<u8>)>,
+}
+
+/// The error returned when a [`Batch`] fails.
+#[derive(Error, Debug)]
+pub enum BatchError {
+    /// The site couldn't be listed before the first operation, so nothing was changed
+    #[error("could not list the site before running the batch: {0}")]
+    Setup(#[source] NeocitiesError),
+    /// An operation failed partway through the batch
+    #[error("batch operation {step} on `{path}` failed: {source}")]
+    Operation {
+        /// Index of the operation that failed, in the order they were queued
+        step: usize,
+        /// Path of the operation that failed
+        path: String,
+        /// Why the operation failed
+        source: NeocitiesError,
+        /// Paths of completed operations that were successfully undone
+        rolled_back: Vec<String>,
+        /// Paths of completed operations that could not be undone, with the reason
+        not_rolled_back: Vec<(String, NeocitiesError)>,
+    },
+}
+
+impl Batch {
+    /// Create an empty [`Batch`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue uploading `data` to `path`
+    pub fn upload(mut self, path: String, data: Vec<u8>) -> Self {
+        self.ops.push(Op::Upload { path, data });
+        self
+    }
+
+    /// Queue deleting the file or directory at `path`
+    pub fn delete(mut self, path: String) -> Self {
+        self.ops.push(Op::Delete { path });
+        self
+    }
+
+    /// Run every queued operation in order against `neocities`,
+    /// undoing completed operations if one of them fails
+    pub async fn execute(self, neocities: &Neocities) -> Result<(), BatchError> {
+        let mut files: HashSet<String> = match neocities.list("").await {
+            Ok(list) => list
+                .into_iter()
+                .filter_map(|entry| match entry {
+                    ListEntry::File { path, .. } => Some(path),
+                    ListEntry::Directory { .. } => None,
+                })
+                .collect(),
+            Err(e) => return Err(BatchError::Setup(e)),
+        };
+
+        let mut completed = Vec::new();
+
+        for (step, op) in self.ops.into_iter().enumerate() {
+            let (path, upload) = match &op {
+                Op::Upload { path, .. } => (path.trim_matches('/').to_string(), true),
+                Op::Delete { path } => (path.trim_matches('/').to_string(), false),
+            };
+
+            let result = match snapshot(neocities, &files, &path).await {
+                Ok(previous) => match op {
+                    Op::Upload { data, .. } => neocities.upload(path.clone(), data).await,
+                    Op::Delete { .. } => neocities.delete([path.clone()]).await,
+                }
+                .map(|_| previous),
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(previous) => {
+                    if upload {
+                        files.insert(path.clone());
+                    } else {
+                        files.retain(|file| !is_within(file, &path));
+                    }
+
+                    completed.push(Completed {
+                        path,
+                        upload,
+                        previous,
+                    });
+                }
+                Err(source) => {
+                    let (rolled_back, not_rolled_back) = rollback(neocities, completed).await;
+
+                    return Err(BatchError::Operation {
+                        step,
+                        path,
+                        source,
+                        rolled_back,
+                        not_rolled_back,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Download every known file at or under `path`
+async fn snapshot(
+    neocities: &Neocities,
+    files: &HashSet<String>,
+    path: &str,
+) -> Result<Vec<(String, Vec<u8>)>, NeocitiesError> {
+    let mut previous = Vec::new();
+
+    for file in files.iter().filter(|file| is_within(file, path)) {
+        previous.push((file.clone(), neocities.download(file).await?));
+    }
+
+    Ok(previous)
+}
+
+// Undo `completed` newest first, returning the paths that were undone and those that weren't
+async fn rollback(
+    neocities: &Neocities,
+    completed: Vec<Completed>,
+) -> (Vec<String>, Vec<(String, NeocitiesError)>) {
+    let mut rolled_back = Vec::new();
+    let mut not_rolled_back = Vec::new();
+
+    for done in completed.into_iter().rev() {
+        let result = match (done.upload, done.previous.is_empty()) {
+            (true, true) => neocities.delete([done.path.clone()]).await.map(|_| ()),
+            // A deleted path with no files saved, such as an empty directory, can't be restored
+            (false, true) => Err(NeocitiesError::InvalidInput(format!(
+                "no files were saved from `{}` to restore",
+                done.path
+            ))),
+            (_, false) => restore(neocities, done.previous).await,
+        };
+
+        match result {
+            Ok(()) => rolled_back.push(done.path),
+            Err(e) => not_rolled_back.push((done.path, e)),
+        }
+    }
+
+    (rolled_back, not_rolled_back)
+}
+
+async fn restore(
+    neocities: &Neocities,
+    previous: Vec<(String, Vec<u8>)>,
+) -> Result<(), NeocitiesError> {
+    for (path, data) in previous {
+        neocities.upload(path, data).await?;
+    }
+
+    Ok(())
+}
+
+fn is_within(file: &str, path: &str) -> bool {
+    file == path
+        || file
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -0,0 +1,234 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE},
+    Request, Response,
+};
+
+use crate::{
+    rate_limit::RequestGovernor, AfterReceive, Auth, BeforeSend, Neocities, NeocitiesError,
+    DEFAULT_MAX_RESPONSE_BYTES,
+};
+
+/// A builder for a [`Neocities`] client with custom connection settings.
+///
+/// Any option not set on the builder uses the default from [`reqwest::ClientBuilder`]
+pub struct NeocitiesBuilder {
+    auth: Auth,
+    client: reqwest::ClientBuilder,
+    base_url: Option<String>,
+    site_url: Option<String>,
+    rate_limit: Option<(u32, Duration)>,
+    accept_language: Option<String>,
+    max_response_bytes: Option<u64>,
+    timeout: Option<Duration>,
+    upload_rate_limit: Option<u64>,
+    before_send: Option<BeforeSend>,
+    after_receive: Option<AfterReceive>,
+}
+
+impl NeocitiesBuilder {
+    /// Start building a [`Neocities`] client authenticated using an API key
+    pub fn key(key: String) -> Self {
+        Self::with_auth(Auth::Key(key))
+    }
+
+    /// Start building a [`Neocities`] client authenticated using a username and password
+    pub fn login(username: String, password: String) -> Self {
+        Self::with_auth(Auth::Login { username, password })
+    }
+
+    fn with_auth(auth: Auth) -> Self {
+        Self {
+            auth,
+            client: reqwest::Client::builder(),
+            base_url: None,
+            site_url: None,
+            rate_limit: None,
+            accept_language: None,
+            max_response_bytes: None,
+            timeout: None,
+            upload_rate_limit: None,
+            before_send: None,
+            after_receive: None,
+        }
+    }
+
+    /// Set the maximum number of idle connections kept open per host.
+    ///
+    /// This only bounds how many connections are kept around for reuse, not how many requests
+    /// can be in flight. Running more concurrent uploads than this will open extra connections
+    /// that are closed once they go idle, so set it at least as high as your expected concurrency
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.client = self.client.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// Set how long an idle connection is kept open for reuse. `None` keeps them open indefinitely
+    pub fn pool_idle_timeout<D: Into<Option<Duration>>>(mut self, timeout: D) -> Self {
+        self.client = self.client.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Set the interval of TCP keepalive probes on open connections. `None` disables them
+    pub fn tcp_keepalive<D: Into<Option<Duration>>>(mut self, interval: D) -> Self {
+        self.client = self.client.tcp_keepalive(interval);
+        self
+    }
+
+    /// Only use HTTP/1.1, never upgrading to HTTP/2.
+    ///
+    /// Useful when a proxy between you and Neocities mishandles HTTP/2 negotiation.
+    /// Overrides any previous call to [`NeocitiesBuilder::http2_prior_knowledge`]
+    pub fn http1_only(mut self) -> Self {
+        self.client = self.client.http1_only();
+        self
+    }
+
+    /// Only use HTTP/2, assuming the server supports it without negotiating first.
+    ///
+    /// Useful when a proxy is known to speak HTTP/2 but breaks the upgrade from HTTP/1.1.
+    /// Overrides any previous call to [`NeocitiesBuilder::http1_only`]
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.client = self.client.http2_prior_knowledge();
+        self
+    }
+
+    /// Set how redirects are followed.
+    ///
+    /// By default up to 10 redirects are followed, and the `Authorization` header along with
+    /// other sensitive headers is removed when a redirect leads to a different host, so
+    /// credentials are never sent anywhere other than Neocities. Use
+    /// [`Policy::none`](reqwest::redirect::Policy::none) to stop following redirects entirely
+    pub fn redirect(mut self, policy: reqwest::redirect::Policy) -> Self {
+        self.client = self.client.redirect(policy);
+        self
+    }
+
+    /// Send API requests to `url` instead of `https://neocities.org/api/`, such as a self-hosted
+    /// server or a local mock. Endpoints are appended to it, so `https://example.com/api` sends
+    /// uploads to `https://example.com/api/upload`. Files are still downloaded from the public
+    /// `neocities.org` address of the site unless [`NeocitiesBuilder::site_url`] is set too.
+    ///
+    /// The URL isn't checked when building, use [`Neocities::check_config`] to catch mistakes early
+    pub fn base_url<T: Into<String>>(mut self, url: T) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Download files from `url` instead of the public `https://<site name>.neocities.org/`
+    /// address of the site, such as a custom domain, the server set with
+    /// [`NeocitiesBuilder::base_url`] or a local mock. File paths are appended to it, so files
+    /// are downloaded from `https://example.com/images/cat.png` with a URL of `https://example.com`.
+    ///
+    /// The URL isn't checked when building, use [`Neocities::check_config`] to catch mistakes early
+    pub fn site_url<T: Into<String>>(mut self, url: T) -> Self {
+        self.site_url = Some(url.into());
+        self
+    }
+
+    /// Limit the client to sending at most `requests` requests every `per`.
+    ///
+    /// The limit is shared by everything using the client, so concurrent operations
+    /// wait on each other instead of each retrying against a rate limited server.
+    /// Requests can be sent in bursts of up to `requests` at once after the client has been idle
+    pub fn rate_limit(mut self, requests: u32, per: Duration) -> Self {
+        self.rate_limit = Some((requests, per));
+        self
+    }
+
+    /// Fail any request that takes longer than `timeout` from being sent until its response
+    /// has been fully read with [`NeocitiesError::Timeout`]. By default requests never time out
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.timeout(timeout);
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send uploaded files at no more than `bytes_per_sec` on average, so a large deploy doesn't
+    /// use up the whole connection. By default uploads are as fast as the connection allows.
+    ///
+    /// Each file is sent in chunks of a tenth of `bytes_per_sec` with pauses in between, so the
+    /// rate evens out over about a tenth of a second. The limit applies to each upload request on
+    /// its own, so concurrent uploads add up. Files passed as a stream instead of bytes in memory
+    /// are not throttled
+    pub fn upload_rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.upload_rate_limit = Some(bytes_per_sec);
+        self
+    }
+
+    /// Call `hook` with every request just before it is sent, such as to log it or add headers.
+    /// Any change the hook makes to the `Authorization` header is undone, so it can't remove
+    /// or replace the credentials of the client
+    pub fn before_send<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut Request) + Send + Sync + 'static,
+    {
+        self.before_send = Some(Arc::new(hook));
+        self
+    }
+
+    /// Call `hook` with every response as soon as it arrives, before its body is read,
+    /// such as to record metrics. Error responses from the API are passed to it as well
+    pub fn after_receive<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Response) + Send + Sync + 'static,
+    {
+        self.after_receive = Some(Arc::new(hook));
+        self
+    }
+
+    /// Send an `Accept-Language` header with every request, such as `en` to keep error
+    /// messages in English should Neocities ever translate them. By default no header is sent
+    /// and the server picks the language. An invalid header value fails [`NeocitiesBuilder::build`]
+    /// with [`NeocitiesError::InvalidConfig`]
+    pub fn accept_language<T: Into<String>>(mut self, language: T) -> Self {
+        self.accept_language = Some(language.into());
+        self
+    }
+
+    /// Fail with [`NeocitiesError::ResponseTooLarge`] instead of reading a response body,
+    /// such as a site listing or a downloaded file, larger than `bytes`.
+    /// The body is counted as it arrives, so an oversized response is never fully held in memory.
+    /// Defaults to [`DEFAULT_MAX_RESPONSE_BYTES`], use `u64::MAX` to remove the limit
+    pub fn max_response_bytes(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = Some(bytes);
+        self
+    }
+
+    /// Build the [`Neocities`] client
+    pub fn build(self) -> Result<Neocities, NeocitiesError> {
+        let mut client = self.client;
+
+        if let Some(language) = self.accept_language {
+            let value = HeaderValue::from_str(&language).map_err(|_| {
+                NeocitiesError::InvalidConfig(format!("invalid Accept-Language `{}`", language))
+            })?;
+
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_LANGUAGE, value);
+            client = client.default_headers(headers);
+        }
+
+        let mut neocities = Neocities::with_client(self.auth, client.build()?);
+        if let Some(url) = self.base_url {
+            neocities.api_url = format!("{}/", url.trim_end_matches('/'));
+        }
+        neocities.site_url = self
+            .site_url
+            .map(|url| format!("{}/", url.trim_end_matches('/')));
+        neocities.governor = self
+            .rate_limit
+            .filter(|(_, per)| !per.is_zero())
+            .map(|(requests, per)| Arc::new(RequestGovernor::new(requests, per)));
+        neocities.max_response_bytes = self
+            .max_response_bytes
+            .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        neocities.timeout = self.timeout;
+        neocities.upload_rate_limit = self.upload_rate_limit;
+        neocities.before_send = self.before_send;
+        neocities.after_receive = self.after_receive;
+
+        Ok(neocities)
+    }
+}
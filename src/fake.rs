@@ -0,0 +1,191 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::{ready, Future},
+    sync::Mutex,
+};
+
+use crate::{sha1_hex, Info, ListEntry, NeocitiesApi, NeocitiesError};
+
+// Every file and directory gets the same timestamp since nothing depends on it
+const TIMESTAMP: &str = "Thu, 01 Jan 1970 00:00:00 -0000";
+
+/// An in-memory stand-in for a Neocities site implementing [`NeocitiesApi`],
+/// for testing code that uses the API without a network connection.
+///
+/// Uploads and deletes change the stored files, and listings are built from them
+/// with real SHA-1 hashes. Errors mimic the server where it matters, such as deleting
+/// a missing path or `index.html`. Every call is recorded and can be read with [`FakeNeocities::calls`].
+///
+/// Only the calls on [`NeocitiesApi`] are available, see its docs for what that leaves out
+#[derive(Debug)]
+pub struct FakeNeocities {
+    site_name: String,
+    key: String,
+    files: Mutex<BTreeMap<String, Vec<u8>>>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl FakeNeocities {
+    /// Create a fake site named `site_name` containing only an empty `index.html`
+    pub fn new<T: Into<String>>(site_name: T) -> Self {
+        let mut files = BTreeMap::new();
+        files.insert("index.html".to_string(), Vec::new());
+
+        Self {
+            site_name: site_name.into(),
+            key: "fake-api-key".to_string(),
+            files: Mutex::new(files),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add a file to the site before using it
+    pub fn with_file<T: Into<String>>(self, path: T, contents: Vec<u8>) -> Self {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.into().trim_matches('/').to_string(), contents);
+        self
+    }
+
+    /// Set the API key returned by [`NeocitiesApi::key`]
+    pub fn with_key<T: Into<String>>(mut self, key: T) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    /// The current contents of every file on the site, by path
+    pub fn files(&self) -> BTreeMap<String, Vec<u8>> {
+        self.files.lock().unwrap().clone()
+    }
+
+    /// Every call made so far, formatted like `upload images/cat.png`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn list_now(&self, path: &str) -> Vec<ListEntry> {
+        let root = path.trim_matches('/');
+        let within = |path: &str| root.is_empty() || path.starts_with(&format!("{}/", root));
+        let files = self.files.lock().unwrap();
+
+        let directories: BTreeSet<&str> = files
+            .keys()
+            .flat_map(|path| path.match_indices('/').map(move |(i, _)| &path[..i]))
+            .filter(|dir| within(dir))
+            .collect();
+
+        let directories = directories.into_iter().map(|path| ListEntry::Directory {
+            path: path.to_string(),
+            updated_at: TIMESTAMP.to_string(),
+        });
+
+        let files = files
+            .iter()
+            .filter(|(path, _)| within(path))
+            .map(|(path, contents)| ListEntry::File {
+                path: path.clone(),
+                size: contents.len() as i64,
+                updated_at: TIMESTAMP.to_string(),
+                sha1_hash: sha1_hex(contents),
+            });
+
+        directories.chain(files).collect()
+    }
+
+    fn delete_now(&self, file_paths: Vec<String>) -> Result<String, NeocitiesError> {
+        let mut files = self.files.lock().unwrap();
+        let is_within =
+            |file: &str, path: &str| file == path || file.starts_with(&format!("{}/", path));
+
+        for path in &file_paths {
+            let path = path.trim_matches('/');
+
+            if path == "index.html" {
+                return Err(NeocitiesError::ApiErr(
+                    "cannot_delete_index".to_string(),
+                    "you cannot delete your index.html file, canceled deleting".to_string(),
+                ));
+            }
+
+            if !files.keys().any(|file| is_within(file, path)) {
+                return Err(NeocitiesError::ApiErr(
+                    "missing_files".to_string(),
+                    format!("{} was not found on your site, canceled deleting", path),
+                ));
+            }
+        }
+
+        for path in &file_paths {
+            files.retain(|file, _| !is_within(file, path.trim_matches('/')));
+        }
+
+        Ok("file(s) have been deleted".to_string())
+    }
+}
+
+impl NeocitiesApi for FakeNeocities {
+    fn list(
+        &self,
+        path: &str,
+    ) -> impl Future<Output = Result<Vec<ListEntry>, NeocitiesError>> + Send {
+        self.record(format!("list {}", path));
+        ready(Ok(self.list_now(path)))
+    }
+
+    fn info(&self, site_name: &str) -> impl Future<Output = Result<Info, NeocitiesError>> + Send {
+        self.record(format!("info {}", site_name));
+
+        let result = if site_name.is_empty() || site_name == self.site_name {
+            Ok(Info {
+                site_name: self.site_name.clone(),
+                hits: 0,
+                views: 0,
+                created_at: TIMESTAMP.to_string(),
+                last_updated: TIMESTAMP.to_string(),
+                domain: None,
+                tags: Vec::new(),
+            })
+        } else {
+            Err(NeocitiesError::ApiErr(
+                "site_not_found".to_string(),
+                "could not find site".to_string(),
+            ))
+        };
+
+        ready(result)
+    }
+
+    fn key(&self) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        self.record("key".to_string());
+        ready(Ok(self.key.clone()))
+    }
+
+    fn upload(
+        &self,
+        file_path: String,
+        file: Vec<u8>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        self.record(format!("upload {}", file_path));
+        self.files
+            .lock()
+            .unwrap()
+            .insert(file_path.trim_matches('/').to_string(), file);
+
+        ready(Ok(
+            "your file(s) have been successfully uploaded".to_string()
+        ))
+    }
+
+    fn delete(
+        &self,
+        file_paths: Vec<String>,
+    ) -> impl Future<Output = Result<String, NeocitiesError>> + Send {
+        self.record(format!("delete {}", file_paths.join(" ")));
+        ready(self.delete_now(file_paths))
+    }
+}
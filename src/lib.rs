@@ -4,27 +4,224 @@
 //!
 //! Start by constructing a [`Neocities`] instance using an API key with [`Neocities::key`]
 //! or a username/password combo using [`Neocities::login`].
+//! If you need to tune the underlying HTTP client, use a [`NeocitiesBuilder`] instead.
 //!
 //! After that you are free to call any methods on the [`Neocities`]
 //! instance to use their respective API calls
+//!
+//! # Features:
+//!
+//! - `archive`: Back up a site to a `.tar.gz` archive with [`Neocities::backup_to_archive`]
+//! - `gzip`: Compress large text files when uploading a directory,
+//!   see [`UploadDirOptions::compress_text`]
+//! - `html`: Find files no page links to with [`Neocities::find_orphans`]
+//! - `sniff`: Detect the MIME type of extensionless files from their contents when uploading
+//!   a directory, see [`UploadDirOptions::sniff_mime_types`]
+//! - `test-util`: An in-memory [`FakeNeocities`] site implementing [`NeocitiesApi`] for tests
+//! - `watch`: Deploy a directory every time it changes with [`Neocities::watch_and_deploy`]
+//! - `zip`: Back up a site to a zip archive with [`Neocities::export_zip`]
+//!   and restore it with [`Neocities::import_zip`]
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    fs::File,
+    io,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::{future::try_join, stream, StreamExt};
 use reqwest::{
+    header::AUTHORIZATION,
     multipart::{Form, Part},
-    Body, RequestBuilder,
+    Body, Method, Request, RequestBuilder, Response, StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 use thiserror::Error;
+use tokio::time::{sleep, Instant};
+
+use rate_limit::{throttle, RequestGovernor};
+
+mod api;
+#[cfg(any(feature = "archive", feature = "zip"))]
+mod archive;
+mod batch;
+mod builder;
+#[cfg(feature = "test-util")]
+mod fake;
+mod multi;
+#[cfg(feature = "html")]
+mod orphans;
+mod rate_limit;
+mod site;
+mod sync;
+mod upload_dir;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use api::NeocitiesApi;
+#[cfg(any(feature = "archive", feature = "zip"))]
+pub use archive::{ExportReport, ImportReport};
+pub use batch::{Batch, BatchError};
+pub use builder::NeocitiesBuilder;
+#[cfg(feature = "test-util")]
+pub use fake::FakeNeocities;
+pub use multi::MultiSite;
+pub use rate_limit::RateLimit;
+pub use site::SiteView;
+pub use sync::{
+    build_manifest, check_case_collisions, DeployReport, DeployState, ListingDiff, LocalFile,
+    LocalManifest, Manifest, PathMismatch, VerifyReport,
+};
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "gzip")]
+pub use upload_dir::COMPRESS_THRESHOLD;
+pub use upload_dir::{SymlinkPolicy, UploadDirOptions, UploadDirReport};
+
+// File types free accounts are allowed to upload
+const FREE_EXTENSIONS: &[&str] = &[
+    "apng",
+    "asc",
+    "atom",
+    "avif",
+    "bin",
+    "cjs",
+    "css",
+    "csv",
+    "dae",
+    "eot",
+    "epub",
+    "geojson",
+    "gif",
+    "glb",
+    "gltf",
+    "gpg",
+    "htm",
+    "html",
+    "ico",
+    "jpeg",
+    "jpg",
+    "js",
+    "json",
+    "key",
+    "kml",
+    "knowl",
+    "less",
+    "manifest",
+    "map",
+    "markdown",
+    "md",
+    "mf",
+    "mid",
+    "midi",
+    "mjs",
+    "mtl",
+    "obj",
+    "opml",
+    "osdx",
+    "otf",
+    "pdf",
+    "pgp",
+    "pls",
+    "png",
+    "py",
+    "rdf",
+    "resolvehandle",
+    "rss",
+    "sass",
+    "scss",
+    "svg",
+    "text",
+    "toml",
+    "ts",
+    "tsv",
+    "ttf",
+    "txt",
+    "webapp",
+    "webmanifest",
+    "webp",
+    "woff",
+    "woff2",
+    "xcf",
+    "xml",
+    "yaml",
+    "yml",
+];
+
+/// The largest response body a client reads unless changed with [`NeocitiesBuilder::max_response_bytes`]
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 256 * 1024 * 1024;
+
+// Longest delay between attempts when polling a site
+const MAX_POLL_DELAY: Duration = Duration::from_secs(30);
 
 const API_URL: &str = "https://neocities.org/api/";
 
+const PLACEHOLDER_INDEX: &str = "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=\"utf-8\">
+    <title>Coming soon</title>
+  </head>
+  <body>
+    <h1>Coming soon</h1>
+  </body>
+</html>
+";
+
+const KEY_ENV: &str = "NEOCITIES_KEY";
+const USERNAME_ENV: &str = "NEOCITIES_USERNAME";
+const PASSWORD_ENV: &str = "NEOCITIES_PASSWORD";
+
 enum Auth {
     Login { username: String, password: String },
     Key(String),
 }
 
+// Written by hand so credentials never end up in logs or panic messages
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Login { username, .. } => f
+                .debug_struct("Login")
+                .field("username", username)
+                .field("password", &"***")
+                .finish(),
+            Auth::Key(_) => f.debug_tuple("Key").field(&"***").finish(),
+        }
+    }
+}
+
 /// The main Neocities API client wrapper.
 pub struct Neocities {
     auth: Auth,
     client: reqwest::Client,
+    api_url: String,
+    site_url: Option<String>,
+    // Name of the authenticated site, looked up on first use
+    site_name: OnceLock<String>,
+    governor: Option<Arc<RequestGovernor>>,
+    rate_limit: Mutex<Option<RateLimit>>,
+    max_response_bytes: u64,
+    timeout: Option<Duration>,
+    upload_rate_limit: Option<u64>,
+    before_send: Option<BeforeSend>,
+    after_receive: Option<AfterReceive>,
+}
+
+pub(crate) type BeforeSend = Arc<dyn Fn(&mut Request) + Send + Sync>;
+pub(crate) type AfterReceive = Arc<dyn Fn(&Response) + Send + Sync>;
+
+impl fmt::Debug for Neocities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Neocities")
+            .field("auth", &self.auth)
+            .field("site_name", &self.site_name.get())
+            .field("rate_limit", &self.rate_limit_status())
+            .finish_non_exhaustive()
+    }
 }
 
 /// A path and its metadata returned by the server.
@@ -43,18 +240,192 @@ pub enum ListEntry {
     },
 }
 
+impl ListEntry {
+    /// The path of this file or directory
+    pub fn path(&self) -> &str {
+        match self {
+            ListEntry::File { path, .. } | ListEntry::Directory { path, .. } => path,
+        }
+    }
+
+    // Remove leading and trailing slashes from the path
+    fn trim_path(mut self) -> Self {
+        match &mut self {
+            ListEntry::File { path, .. } | ListEntry::Directory { path, .. } => {
+                if path.starts_with('/') || path.ends_with('/') {
+                    *path = path.trim_matches('/').to_string();
+                }
+            }
+        }
+
+        self
+    }
+
+    /// When this file or directory was last updated, parsed from `updated_at`.
+    /// Returns `None` if the server sent a timestamp in an unexpected format
+    pub fn updated_at_utc(&self) -> Option<DateTime<Utc>> {
+        let updated_at = match self {
+            ListEntry::File { updated_at, .. } | ListEntry::Directory { updated_at, .. } => {
+                updated_at
+            }
+        };
+
+        DateTime::parse_from_rfc2822(updated_at)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Shows the path of the entry, with a trailing `/` for directories and the size for files
+impl fmt::Display for ListEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListEntry::File { path, size, .. } => write!(f, "{} ({} bytes)", path, size),
+            ListEntry::Directory { path, .. } => write!(f, "{}/", path),
+        }
+    }
+}
+
+/// The structure and info of a site at one point in time, from [`Neocities::export_metadata`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SiteSnapshot {
+    pub info: Info,
+    pub listing: Vec<ListEntry>,
+    /// When the snapshot was taken, in RFC 3339 format
+    pub captured_at: String,
+}
+
+/// A page of a site listing from [`Neocities::list_page`].
+#[derive(Debug)]
+pub struct ListPage {
+    /// The entries on this page, sorted by path
+    pub entries: Vec<ListEntry>,
+    /// Number of entries in the whole listing
+    pub total: usize,
+    /// Whether there are more entries after this page
+    pub has_more: bool,
+}
+
+impl ListPage {
+    /// Take the page of up to `limit` entries starting at `offset` from a full listing,
+    /// after sorting it by path
+    pub fn from_entries(mut entries: Vec<ListEntry>, offset: usize, limit: usize) -> Self {
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+
+        let total = entries.len();
+        let entries: Vec<ListEntry> = entries.into_iter().skip(offset).take(limit).collect();
+
+        Self {
+            has_more: offset.saturating_add(entries.len()) < total,
+            entries,
+            total,
+        }
+    }
+}
+
+/// Whether [`Neocities::upload_if_changed`] or [`Neocities::upload_if_newer`] uploaded a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadOutcome {
+    /// The file was new or different and was uploaded
+    Uploaded,
+    /// The file on the site was already up to date, so nothing was sent
+    Unchanged,
+}
+
+/// The result of [`Neocities::upload_many_with_report`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UploadManyReport {
+    /// The success message sent by the server
+    pub message: String,
+    /// Groups of paths in the upload with byte-identical contents, only including
+    /// groups with more than one path
+    pub duplicates: Vec<Vec<String>>,
+}
+
+/// What [`Neocities::delete_all`] does with `index.html`, which every site is required to have.
+#[derive(Debug, Clone, Default)]
+pub enum IndexHtml {
+    /// Leave the current `index.html` as is
+    #[default]
+    Keep,
+    /// Replace `index.html` with a simple "coming soon" page
+    Placeholder,
+    /// Replace `index.html` with the given contents
+    Replace(Vec<u8>),
+}
+
+/// The result of [`Neocities::delete_all`].
+#[derive(Debug, Default)]
+pub struct DeleteAllReport {
+    /// Paths of files that were deleted
+    pub deleted_files: Vec<String>,
+    /// Paths of files that could not be deleted, with the reason
+    pub failed_files: Vec<(String, NeocitiesError)>,
+    /// Paths of directories that were deleted
+    pub deleted_dirs: Vec<String>,
+    /// Paths of directories that could not be deleted, with the reason
+    pub failed_dirs: Vec<(String, NeocitiesError)>,
+    /// Why `index.html` could not be replaced, if it couldn't
+    pub index_error: Option<NeocitiesError>,
+}
+
+impl DeleteAllReport {
+    /// Whether everything was deleted and `index.html` was replaced as asked,
+    /// leaving only `index.html` on the site
+    pub fn is_complete(&self) -> bool {
+        self.failed_files.is_empty() && self.failed_dirs.is_empty() && self.index_error.is_none()
+    }
+}
+
 /// Info about a Neocities site
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Info {
     #[serde(rename = "sitename")]
     pub site_name: String,
     pub hits: i64,
+    #[serde(default)]
+    pub views: i64,
     pub created_at: String,
     pub last_updated: String,
     pub domain: Option<String>,
     pub tags: Vec<String>,
 }
 
+impl Info {
+    /// The file extensions an account can upload, or `None` if any type of file is allowed.
+    ///
+    /// Only supporter accounts can upload any type of file. The API doesn't say whether an
+    /// account is a supporter, so `supporter` has to come from the caller.
+    /// Extensions are lowercase and have no leading `.`
+    pub fn allowed_extensions(supporter: bool) -> Option<&'static [&'static str]> {
+        if supporter {
+            None
+        } else {
+            Some(FREE_EXTENSIONS)
+        }
+    }
+
+    /// Check whether an account is allowed to upload a file named `filename` because of its
+    /// extension, see [`Info::allowed_extensions`]. Set `supporter` for supporter accounts,
+    /// which can upload anything. `false` means the server would reject the file
+    pub fn can_upload(filename: &str, supporter: bool) -> bool {
+        Info::allowed_extensions(supporter)
+            .is_none_or(|allowed| extension_allowed(allowed, Path::new(filename)))
+    }
+}
+
+/// View statistics for a Neocities site.
+///
+/// The Neocities API only reports lifetime totals, so no per-day history is available
+#[derive(Serialize, Deserialize, Debug)]
+#[non_exhaustive]
+pub struct SiteStats {
+    /// Total number of hits the site has received
+    pub hits: i64,
+    /// Total number of unique views the site has received
+    pub views: i64,
+}
+
 // Generic type for handling the `result` field in all API responses
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "result")]
@@ -77,7 +448,7 @@ enum ApiResult<T> {
 }
 
 impl<T> ApiResult<T> {
-    fn to_result(self) -> Result<T, NeocitiesError> {
+    fn into_result(self) -> Result<T, NeocitiesError> {
         match self {
             ApiResult::Success { data } => Ok(data),
             ApiResult::Error {
@@ -94,102 +465,1112 @@ impl<T> ApiResult<T> {
 impl Neocities {
     /// Create a new [`Neocities`] client authenticated using an API key
     pub fn new(key: String) -> Self {
-        let client = reqwest::Client::new();
+        Self::with_client(Auth::Key(key), reqwest::Client::new())
+    }
+
+    /// Create a new [`Neocities`] client authenticated using a username and password
+    pub fn login(username: String, password: String) -> Self {
+        let auth = Auth::Login { username, password };
 
+        Self::with_client(auth, reqwest::Client::new())
+    }
+
+    fn with_client(auth: Auth, client: reqwest::Client) -> Self {
         Self {
-            auth: Auth::Key(key),
+            auth,
             client,
+            api_url: API_URL.to_string(),
+            site_url: None,
+            site_name: OnceLock::new(),
+            governor: None,
+            rate_limit: Mutex::new(None),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            timeout: None,
+            upload_rate_limit: None,
+            before_send: None,
+            after_receive: None,
         }
     }
 
-    /// Create a new [`Neocities`] client authenticated using a username and password
-    pub fn login(username: String, password: String) -> Self {
-        let client = reqwest::Client::new();
-        let auth = Auth::Login { username, password };
+    // A client for the same server and with the same settings as this one, authenticated
+    // with `auth` instead. The request rate limit is shared with this client
+    fn with_auth(&self, auth: Auth) -> Self {
+        Self {
+            api_url: self.api_url.clone(),
+            site_url: self.site_url.clone(),
+            governor: self.governor.clone(),
+            rate_limit: Mutex::new(self.rate_limit_status()),
+            max_response_bytes: self.max_response_bytes,
+            timeout: self.timeout,
+            upload_rate_limit: self.upload_rate_limit,
+            before_send: self.before_send.clone(),
+            after_receive: self.after_receive.clone(),
+            ..Self::with_client(auth, self.client.clone())
+        }
+    }
 
-        Self { client, auth }
+    /// Create a new [`Neocities`] client using credentials from the environment.
+    ///
+    /// An API key in `NEOCITIES_KEY` is preferred, otherwise `NEOCITIES_USERNAME` and
+    /// `NEOCITIES_PASSWORD` are used. If neither is set [`NeocitiesError::MissingCredentials`]
+    /// is returned with the names of the variables that were checked
+    pub fn from_env() -> Result<Self, NeocitiesError> {
+        if let Ok(key) = std::env::var(KEY_ENV) {
+            return Ok(Self::new(key));
+        }
+
+        match (std::env::var(USERNAME_ENV), std::env::var(PASSWORD_ENV)) {
+            (Ok(username), Ok(password)) => Ok(Self::login(username, password)),
+            _ => Err(NeocitiesError::MissingCredentials(vec![
+                KEY_ENV.to_string(),
+                USERNAME_ENV.to_string(),
+                PASSWORD_ENV.to_string(),
+            ])),
+        }
     }
 
     /// Get a list of files in the authorized site. `path` can be used to specify
     /// which directory to list the files in. If `path` is empty it will list all items.
+    ///
+    /// Leading and trailing slashes on `path` are ignored, so `images`, `/images` and `images/`
+    /// all list the same directory. Returned paths never start or end with a slash, any the
+    /// server sends are removed.
+    ///
+    /// An empty site or directory gives an empty list, including when the server
+    /// leaves out the `files` field of its response entirely
     pub async fn list<T: AsRef<str>>(&self, path: T) -> Result<Vec<ListEntry>, NeocitiesError> {
-        let mut request = self.client.get(API_URL.to_string() + "list");
-        request = add_authorization_header(request, &self.auth);
+        let path = path.as_ref().trim_matches('/');
+        let mut request = self.request(Method::GET, "list");
 
-        if !path.as_ref().is_empty() {
-            request = request.form(&[("path", path.as_ref())]);
+        if !path.is_empty() {
+            request = request.form(&[("path", path)]);
         }
 
-        let response = request.send().await?.error_for_status()?;
-        response
-            .json::<ApiResult<Vec<ListEntry>>>()
+        let response = self.send(request).await?.error_for_status()?;
+        let files = self
+            .read_json::<ApiResult<Option<Vec<ListEntry>>>>(response)
             .await?
-            .to_result()
+            .into_result()?;
+
+        Ok(files
+            .unwrap_or_default()
+            .into_iter()
+            .map(ListEntry::trim_path)
+            .collect())
+    }
+
+    /// Get a list of files in the authorized site like [`Neocities::list`],
+    /// indexed by the path of each file and directory
+    pub async fn list_map<T: AsRef<str>>(
+        &self,
+        path: T,
+    ) -> Result<HashMap<String, ListEntry>, NeocitiesError> {
+        Ok(self
+            .list(path)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.path().to_string(), entry))
+            .collect())
+    }
+
+    /// Get one page of the list of files in the authorized site, for paging through a large
+    /// site in a UI. Entries are sorted by path, and the page holds up to `limit` of them
+    /// starting at `offset`.
+    ///
+    /// The API always returns the whole listing, so this lists the site every time it is
+    /// called. To show several pages from one listing, keep the result of [`Neocities::list`]
+    /// and use [`ListPage::from_entries`] instead
+    pub async fn list_page<T: AsRef<str>>(
+        &self,
+        path: T,
+        offset: usize,
+        limit: usize,
+    ) -> Result<ListPage, NeocitiesError> {
+        Ok(ListPage::from_entries(
+            self.list(path).await?,
+            offset,
+            limit,
+        ))
+    }
+
+    /// Get just the paths of the entries in the authorized site, such as for printing them one
+    /// per line. Works like [`Neocities::list`], leaving out directories if `files_only` is set
+    pub async fn paths<T: AsRef<str>>(
+        &self,
+        path: T,
+        files_only: bool,
+    ) -> Result<Vec<String>, NeocitiesError> {
+        Ok(self
+            .list(path)
+            .await?
+            .into_iter()
+            .filter(|entry| !files_only || matches!(entry, ListEntry::File { .. }))
+            .map(|entry| entry.path().to_string())
+            .collect())
+    }
+
+    /// Get every file in the authorized site grouped by lowercase extension without the `.`,
+    /// such as `png` or `html`. Files without an extension are grouped under `""`.
+    /// Directories are left out
+    pub async fn list_by_extension(
+        &self,
+    ) -> Result<HashMap<String, Vec<ListEntry>>, NeocitiesError> {
+        let mut groups: HashMap<String, Vec<ListEntry>> = HashMap::new();
+
+        for entry in self.list("").await? {
+            if let ListEntry::Directory { .. } = entry {
+                continue;
+            }
+
+            let extension = Path::new(entry.path())
+                .extension()
+                .map_or(String::new(), |ext| {
+                    ext.to_string_lossy().to_ascii_lowercase()
+                });
+            groups.entry(extension).or_default().push(entry);
+        }
+
+        Ok(groups)
+    }
+
+    /// Get the paths of all directories under `path` in the authorized site, sorted.
+    /// Parent directories implied by file paths are included even if the server
+    /// did not list them. If `path` is empty it will list all directories
+    pub async fn directories<T: AsRef<str>>(&self, path: T) -> Result<Vec<String>, NeocitiesError> {
+        let root = path.as_ref().trim_matches('/');
+        let mut directories = BTreeSet::new();
+
+        for entry in self.list(root).await? {
+            if let ListEntry::Directory { path, .. } = &entry {
+                directories.insert(path.clone());
+            }
+
+            let mut parent = entry.path();
+            while let Some((dir, _)) = parent.rsplit_once('/') {
+                if root.is_empty() || dir.starts_with(&format!("{}/", root)) {
+                    directories.insert(dir.to_string());
+                }
+                parent = dir;
+            }
+        }
+
+        Ok(directories.into_iter().collect())
+    }
+
+    /// Find files on the authorized site with identical contents, using the SHA-1 hashes in the
+    /// site listing so nothing is downloaded. Returns groups of paths that share a hash,
+    /// only including groups with more than one file. Directories are ignored
+    pub async fn find_duplicates(&self) -> Result<Vec<Vec<String>>, NeocitiesError> {
+        let mut by_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for entry in self.list("").await? {
+            if let ListEntry::File {
+                path, sha1_hash, ..
+            } = entry
+            {
+                by_hash
+                    .entry(sha1_hash.to_ascii_lowercase())
+                    .or_default()
+                    .push(path);
+            }
+        }
+
+        Ok(by_hash
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .collect())
     }
 
     /// Get info about a Neocities site.
     /// If `site_name` is empty it will get info about the site used for authentication
     pub async fn info<T: AsRef<str>>(&self, site_name: T) -> Result<Info, NeocitiesError> {
-        let mut request = self.client.get(API_URL.to_string() + "info");
-        request = add_authorization_header(request, &self.auth);
+        let mut request = self.request(Method::GET, "info");
 
         if !site_name.as_ref().is_empty() {
+            validate_site_name(site_name.as_ref())?;
             request = request.form(&[("sitename", site_name.as_ref())]);
         }
 
-        let response = request.send().await?.error_for_status()?;
-        response.json::<ApiResult<Info>>().await?.to_result()
+        let response = self.send(request).await?.error_for_status()?;
+        self.read_json::<ApiResult<Info>>(response)
+            .await?
+            .into_result()
+    }
+
+    /// Get view statistics for a Neocities site.
+    /// If `site_name` is empty it will get stats for the site used for authentication.
+    ///
+    /// The API does not expose historical (e.g. daily) statistics, only the
+    /// lifetime totals also found in [`Info`], so this is currently a focused view over [`Neocities::info`]
+    pub async fn stats<T: AsRef<str>>(&self, site_name: T) -> Result<SiteStats, NeocitiesError> {
+        let info = self.info(site_name).await?;
+
+        Ok(SiteStats {
+            hits: info.hits,
+            views: info.views,
+        })
+    }
+
+    /// Get the total number of hits of a Neocities site, as in [`Info::hits`].
+    /// If `site_name` is empty it will get the hits of the site used for authentication
+    pub async fn hit_count<T: AsRef<str>>(&self, site_name: T) -> Result<i64, NeocitiesError> {
+        Ok(self.info(site_name).await?.hits)
+    }
+
+    /// Get when a Neocities site was last updated, as in [`Info::last_updated`].
+    /// If `site_name` is empty it will get the time for the site used for authentication
+    pub async fn last_updated<T: AsRef<str>>(
+        &self,
+        site_name: T,
+    ) -> Result<String, NeocitiesError> {
+        Ok(self.info(site_name).await?.last_updated)
+    }
+
+    /// Take a [`SiteSnapshot`] of the [`Info`] and full listing of the authorized site without
+    /// downloading any files, such as for saving as JSON and comparing over time
+    pub async fn export_metadata(&self) -> Result<SiteSnapshot, NeocitiesError> {
+        let (info, listing) = try_join(self.info(""), self.list("")).await?;
+
+        Ok(SiteSnapshot {
+            info,
+            listing,
+            captured_at: Utc::now().to_rfc3339(),
+        })
     }
 
     /// Get the API key for the currently authorized account.
-    /// If the account has no current key, one will be newly generated
+    /// If the account has no current key, one will be newly generated.
+    ///
+    /// Whitespace around the key is removed, and [`NeocitiesError::InvalidResponse`] is returned
+    /// if the server sends an empty key or one containing whitespace
     pub async fn key(&self) -> Result<String, NeocitiesError> {
-        let mut request = self.client.get(API_URL.to_string() + "key");
-        request = add_authorization_header(request, &self.auth);
+        let request = self.request(Method::GET, "key");
+
+        let response = self.send(request).await?.error_for_status()?;
+        let key = self
+            .read_json::<ApiResult<String>>(response)
+            .await?
+            .into_result()?;
+        let key = key.trim();
 
-        let response = request.send().await?.error_for_status()?;
-        response.json::<ApiResult<String>>().await?.to_result()
+        // The key itself is left out of the error so it doesn't end up in logs
+        if key.is_empty() {
+            return Err(NeocitiesError::InvalidResponse(
+                "server returned an empty API key".to_string(),
+            ));
+        }
+
+        if key.contains(char::is_whitespace) {
+            return Err(NeocitiesError::InvalidResponse(format!(
+                "server returned an API key of {} characters containing whitespace",
+                key.chars().count()
+            )));
+        }
+
+        Ok(key.to_string())
+    }
+
+    /// Get the API key for the currently authorized account like [`Neocities::key`], then check
+    /// that it works by getting the site's [`Info`] with it before returning it.
+    /// The check goes to the same server with the same settings as this client.
+    /// Useful before storing a newly generated key
+    pub async fn key_and_verify(&self) -> Result<String, NeocitiesError> {
+        let key = self.key().await?;
+
+        self.with_auth(Auth::Key(key.clone())).info("").await?;
+
+        Ok(key)
     }
 
     /// Upload a file to the current [`Neocities`] site.
-    /// Returns the success message sent by the server
+    /// Returns the success message sent by the server.
+    ///
+    /// The API has no way to set a file's modification time, so the `updated_at` of an
+    /// uploaded file is always the time it was uploaded. Original timestamps can't be
+    /// preserved when migrating a site.
+    ///
+    /// There is also no way to purge the CDN cache, so the old version of a file may still be
+    /// served for a while after it is replaced. Use [`Neocities::await_propagation`] to wait
+    /// until the new version is live
     pub async fn upload<T: Into<Body>>(
         &self,
         file_path: String,
         file: T,
     ) -> Result<String, NeocitiesError> {
-        let part = Part::stream(file).file_name(file_path.clone());
+        validate_path(&file_path)?;
+
+        let part = self.file_part(file).file_name(file_path.clone());
         let form = Form::new().part(file_path, part);
 
-        let mut request = self.client.post(API_URL.to_string() + "upload");
-        request = add_authorization_header(request, &self.auth);
-        request = request.multipart(form);
+        self.upload_form(form).await
+    }
+
+    /// Upload a file to the current [`Neocities`] site like [`Neocities::upload`], adding each of
+    /// `fields` to the request as an extra text field after the file.
+    /// Returns the success message sent by the server.
+    ///
+    /// The server currently ignores any fields it doesn't know, so this only exists
+    /// to pass along parameters added to the API before this crate supports them
+    pub async fn upload_with_fields<T: Into<Body>>(
+        &self,
+        file_path: String,
+        file: T,
+        fields: Vec<(String, String)>,
+    ) -> Result<String, NeocitiesError> {
+        validate_path(&file_path)?;
 
-        let response = request.send().await?;
+        let part = self.file_part(file).file_name(file_path.clone());
+        let form = fields
+            .into_iter()
+            .fold(Form::new().part(file_path, part), |form, (name, value)| {
+                form.text(name, value)
+            });
 
-        response.json::<ApiResult<String>>().await?.to_result()
+        self.upload_form(form).await
+    }
+
+    /// Upload several files to the current [`Neocities`] site in a single request.
+    /// Each file is a pair of its path on the site and its contents.
+    /// Returns the success message sent by the server.
+    ///
+    /// If two files have the same path, ignoring leading and trailing slashes,
+    /// [`NeocitiesError::InvalidInput`] is returned naming the path and nothing is uploaded.
+    /// Files with identical contents at different paths are each uploaded in full, since the API
+    /// has no way to point one path at contents already sent. Use
+    /// [`Neocities::upload_many_with_report`] to find out which files are duplicates
+    pub async fn upload_many<T: Into<Body>>(
+        &self,
+        files: Vec<(String, T)>,
+    ) -> Result<String, NeocitiesError> {
+        let mut form = Form::new();
+        check_duplicates(files.iter().map(|(path, _)| path.as_str()))?;
+
+        for (file_path, file) in files {
+            validate_path(&file_path)?;
+
+            let part = self.file_part(file).file_name(file_path.clone());
+            form = form.part(file_path, part);
+        }
+
+        self.upload_form(form).await
+    }
+
+    /// Upload several files like [`Neocities::upload_many`], also reporting which of them have
+    /// identical contents by comparing their SHA-1 hashes.
+    ///
+    /// Every file is still uploaded, duplicates included, so this only helps to understand
+    /// what a deploy is sending, such as the same image copied into several directories
+    pub async fn upload_many_with_report(
+        &self,
+        files: Vec<(String, Vec<u8>)>,
+    ) -> Result<UploadManyReport, NeocitiesError> {
+        let mut by_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for (path, file) in &files {
+            by_hash
+                .entry(sha1_hex(file))
+                .or_default()
+                .push(path.trim_matches('/').to_string());
+        }
+
+        let message = self.upload_many(files).await?;
+
+        Ok(UploadManyReport {
+            message,
+            duplicates: by_hash
+                .into_values()
+                .filter(|paths| paths.len() > 1)
+                .collect(),
+        })
     }
 
     /// Delete files from the current [`Neocities`] site.
-    /// Returns the success message sent by the server
+    /// Returns the success message sent by the server, or [`NeocitiesError::InvalidInput`]
+    /// without sending anything if a path is the site root or contains `.` or `..`
     pub async fn delete<T: AsRef<[String]>>(
         &self,
         file_paths: T,
     ) -> Result<String, NeocitiesError> {
-        let mut request = self.client.post(API_URL.to_string() + "delete");
-        request = add_authorization_header(request, &self.auth);
+        let mut request = self.request(Method::POST, "delete");
 
         for path in file_paths.as_ref() {
-            request = request.query(&[("filenames[]", path.as_str())]);
+            request = request.query(&[("filenames[]", validate_path(path)?)]);
         }
 
-        request
-            .send()
+        let response = self.send(request).await?;
+        self.read_json::<ApiResult<String>>(response)
             .await?
-            .json::<ApiResult<String>>()
+            .into_result()
+    }
+
+    /// Delete files from the current [`Neocities`] site, first checking that every path exists.
+    /// If any of `file_paths` are missing nothing is deleted and [`NeocitiesError::MissingFiles`]
+    /// is returned with the missing paths.
+    ///
+    /// This costs an extra [`Neocities::list`] call of the whole site before deleting
+    pub async fn delete_strict<T: AsRef<[String]>>(
+        &self,
+        file_paths: T,
+    ) -> Result<String, NeocitiesError> {
+        let existing = self.list("").await?;
+
+        let missing: Vec<String> = file_paths
+            .as_ref()
+            .iter()
+            .filter(|path| {
+                let path = path.trim_matches('/');
+                !existing.iter().any(|entry| entry.path() == path)
+            })
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(NeocitiesError::MissingFiles(missing));
+        }
+
+        self.delete(file_paths).await
+    }
+
+    /// Download a file from the current [`Neocities`] site.
+    ///
+    /// The API has no download endpoint, so the file is fetched from the public
+    /// `https://<site name>.neocities.org/` URL, or the URL set with [`NeocitiesBuilder::site_url`].
+    /// The site name is looked up with [`Neocities::info`] on the first download and reused afterwards
+    pub async fn download(&self, path: &str) -> Result<Vec<u8>, NeocitiesError> {
+        let url = self.public_url(validate_path(path)?).await?;
+
+        let response = self.send(self.client.get(url)).await?.error_for_status()?;
+        self.read_body(response).await
+    }
+
+    /// Download a file from the current [`Neocities`] site like [`Neocities::download`], calling
+    /// `progress` as the file arrives with the number of bytes received so far and the total
+    /// size, if the server sent a `Content-Length`.
+    ///
+    /// A download that takes longer than the timeout set with [`NeocitiesBuilder::timeout`]
+    /// fails with [`NeocitiesError::Timeout`]
+    pub async fn download_with_progress<F>(
+        &self,
+        path: &str,
+        mut progress: F,
+    ) -> Result<Vec<u8>, NeocitiesError>
+    where
+        F: FnMut(u64, Option<u64>) + Send,
+    {
+        let url = self.public_url(validate_path(path)?).await?;
+
+        let response = self.send(self.client.get(url)).await?.error_for_status()?;
+        self.read_body_with_progress(response, &mut progress).await
+    }
+
+    /// Download a file from the current [`Neocities`] site to `local_path`, creating any
+    /// missing parent directories. See [`Neocities::download`]
+    pub async fn download_to<P: AsRef<Path>>(
+        &self,
+        path: &str,
+        local_path: P,
+    ) -> Result<(), NeocitiesError> {
+        let local_path = local_path.as_ref();
+        let file = self.download(path).await?;
+
+        if let Some(parent) = local_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Ok(std::fs::write(local_path, file)?)
+    }
+
+    /// Download several files from the current [`Neocities`] site like [`Neocities::download`],
+    /// with up to `max_concurrent` downloads in flight at once. A failed download doesn't stop
+    /// the others, so each path is returned with its own result, in the same order as `paths`.
+    ///
+    /// Downloads still go through the rate limit set with [`NeocitiesBuilder::rate_limit`]
+    pub async fn download_many(
+        &self,
+        paths: &[String],
+        max_concurrent: usize,
+    ) -> Vec<(String, Result<Vec<u8>, NeocitiesError>)> {
+        self.download_many_cancellable(paths, max_concurrent, &CancellationToken::new())
+            .await
+    }
+
+    /// Download several files like [`Neocities::download_many`], stopping early once `cancel`
+    /// is cancelled. Downloads already in flight are allowed to finish, and every path that
+    /// wasn't started yet is returned with [`NeocitiesError::Cancelled`]
+    pub async fn download_many_cancellable(
+        &self,
+        paths: &[String],
+        max_concurrent: usize,
+        cancel: &CancellationToken,
+    ) -> Vec<(String, Result<Vec<u8>, NeocitiesError>)> {
+        stream::iter(paths)
+            .map(|path| async move {
+                if cancel.is_cancelled() {
+                    return (path.clone(), Err(NeocitiesError::Cancelled));
+                }
+
+                (path.clone(), self.download(path).await)
+            })
+            .buffered(max_concurrent.max(1))
+            .collect()
+            .await
+    }
+
+    /// Wait until the current [`Neocities`] site responds to a GET request of its public
+    /// `https://<site name>.neocities.org/` URL with `200 OK`, such as right after creating it.
+    /// If a URL was set with [`NeocitiesBuilder::site_url`] that is polled instead.
+    /// The site is polled with increasing delays between attempts, and
+    /// [`NeocitiesError::Timeout`] is returned if it isn't live within `timeout`
+    pub async fn await_site_live(&self, timeout: Duration) -> Result<(), NeocitiesError> {
+        let url = self.public_url("").await?;
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_secs(1);
+
+        loop {
+            // Connection errors are expected while the site is being set up
+            if let Ok(response) = self.send(self.client.get(&url)).await {
+                if response.status() == StatusCode::OK {
+                    return Ok(());
+                }
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(NeocitiesError::Timeout(timeout));
+            }
+
+            sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(MAX_POLL_DELAY);
+        }
+    }
+
+    /// Wait until the public copy of the file at `path` on the current [`Neocities`] site matches
+    /// the SHA-1 hash in the site listing, such as after replacing it with [`Neocities::upload`].
+    ///
+    /// Neocities has no way to purge its CDN cache, so this downloads the file with increasing
+    /// delays between attempts until the cached copy is replaced. [`NeocitiesError::Timeout`]
+    /// is returned if it still differs after `timeout`, and [`NeocitiesError::MissingFiles`]
+    /// if `path` isn't a file on the site
+    pub async fn await_propagation(
+        &self,
+        path: &str,
+        timeout: Duration,
+    ) -> Result<(), NeocitiesError> {
+        let expected = match self.get_metadata(path).await? {
+            Some(ListEntry::File { sha1_hash, .. }) => sha1_hash,
+            _ => return Err(NeocitiesError::MissingFiles(vec![path.to_string()])),
+        };
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_secs(1);
+
+        loop {
+            // The old copy may be gone from the CDN before the new one arrives
+            if let Ok(file) = self.download(path).await {
+                if sha1_hex(&file).eq_ignore_ascii_case(&expected) {
+                    return Ok(());
+                }
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(NeocitiesError::Timeout(timeout));
+            }
+
+            sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(MAX_POLL_DELAY);
+        }
+    }
+
+    /// Delete every file and directory from the current [`Neocities`] site, returning a
+    /// [`DeleteAllReport`] of what was deleted and what is left.
+    ///
+    /// `index.html` can't be deleted, so it is kept or replaced depending on `index`.
+    /// Use [`IndexHtml::Placeholder`] or [`IndexHtml::Replace`] to fully reset a site.
+    /// Files are deleted one at a time, then directories from the deepest up, so a directory
+    /// only fails to delete if something is actually wrong with it. Failing to delete a file or
+    /// directory doesn't stop the rest, only failing to list the site does. If `index.html` can't
+    /// be replaced the error is kept in [`DeleteAllReport::index_error`]
+    pub async fn delete_all(&self, index: IndexHtml) -> Result<DeleteAllReport, NeocitiesError> {
+        let list = self.list("").await?;
+        let mut report = DeleteAllReport::default();
+        let mut dirs = Vec::new();
+
+        for entry in list {
+            match entry {
+                ListEntry::File { path, .. } if path == "index.html" => {}
+                ListEntry::File { path, .. } => match self.delete([path.clone()]).await {
+                    Ok(_) => report.deleted_files.push(path),
+                    Err(e) => report.failed_files.push((path, e)),
+                },
+                ListEntry::Directory { path, .. } => dirs.push(path),
+            }
+        }
+
+        dirs.sort_by_key(|path| std::cmp::Reverse(path.matches('/').count()));
+
+        for path in dirs {
+            match self.delete([path.clone()]).await {
+                Ok(_) => report.deleted_dirs.push(path),
+                Err(e) => report.failed_dirs.push((path, e)),
+            }
+        }
+
+        let contents = match index {
+            IndexHtml::Keep => None,
+            IndexHtml::Placeholder => Some(Body::from(PLACEHOLDER_INDEX)),
+            IndexHtml::Replace(contents) => Some(Body::from(contents)),
+        };
+
+        if let Some(contents) = contents {
+            report.index_error = self.upload("index.html".to_string(), contents).await.err();
+        }
+
+        Ok(report)
+    }
+
+    /// Count how many files and directories [`Neocities::delete_all`] would delete,
+    /// without deleting anything. `index.html` is never counted since it can't be deleted
+    pub async fn delete_all_count(&self) -> Result<usize, NeocitiesError> {
+        Ok(self
+            .list("")
+            .await?
+            .iter()
+            .filter(|entry| entry.path() != "index.html")
+            .count())
+    }
+
+    /// Count how many files and directories deleting the directory `dir` would remove,
+    /// including `dir` itself, without deleting anything
+    pub async fn delete_dir_count(&self, dir: &str) -> Result<usize, NeocitiesError> {
+        let dir = validate_path(dir)?;
+        Ok(self.list(dir).await?.len() + 1)
+    }
+
+    /// Delete files from the current [`Neocities`] site one at a time, returning the result for each path.
+    ///
+    /// The server cancels a whole [`Neocities::delete`] if any path is missing, while this reports
+    /// exactly which paths failed. Missing paths fail with an [`ApiErrorKind::MissingFiles`] error,
+    /// so callers can treat files that are already gone as deleted.
+    /// This sends one request per path
+    pub async fn delete_detailed<T: AsRef<[String]>>(
+        &self,
+        file_paths: T,
+    ) -> Vec<(String, Result<String, NeocitiesError>)> {
+        let mut results = Vec::new();
+
+        for path in file_paths.as_ref() {
+            results.push((path.clone(), self.delete([path.clone()]).await));
+        }
+
+        results
+    }
+
+    /// Get the metadata of a single file or directory on the current [`Neocities`] site.
+    /// Returns `None` if nothing exists at `path`, or [`NeocitiesError::InvalidInput`]
+    /// if `path` is empty since the root of the site has no metadata.
+    ///
+    /// This lists the parent directory of `path`, so it costs as much as a [`Neocities::list`] call
+    pub async fn get_metadata(&self, path: &str) -> Result<Option<ListEntry>, NeocitiesError> {
+        let path = validate_path(path)?;
+        let parent = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        Ok(self
+            .list(parent)
             .await?
-            .to_result()
+            .into_iter()
+            .find(|entry| entry.path() == path))
+    }
+
+    /// Upload a file to the current [`Neocities`] site, then check that the SHA-1 hash
+    /// reported by the server matches `file`. Returns [`NeocitiesError::HashMismatch`]
+    /// if the file is missing or differs after uploading.
+    ///
+    /// Verifying costs an extra [`Neocities::get_metadata`] call after the upload
+    pub async fn upload_verified(
+        &self,
+        file_path: String,
+        file: Vec<u8>,
+    ) -> Result<(), NeocitiesError> {
+        let expected = sha1_hex(&file);
+        self.upload(file_path.clone(), file).await?;
+
+        let actual = match self.get_metadata(&file_path).await? {
+            Some(ListEntry::File { sha1_hash, .. }) => Some(sha1_hash.to_ascii_lowercase()),
+            _ => None,
+        };
+
+        if actual.as_deref() != Some(expected.as_str()) {
+            return Err(NeocitiesError::HashMismatch {
+                path: file_path,
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the file at `local_path` matches the file at `remote_path`
+    /// on the current [`Neocities`] site by comparing their SHA-1 hashes.
+    /// Returns `false` if the remote file does not exist
+    pub async fn verify_file<P: AsRef<Path>>(
+        &self,
+        remote_path: &str,
+        local_path: P,
+    ) -> Result<bool, NeocitiesError> {
+        let local_hash = sha1_file(local_path)?;
+
+        let remote_hash = match self.get_metadata(remote_path).await? {
+            Some(ListEntry::File { sha1_hash, .. }) => Some(sha1_hash),
+            _ => None,
+        };
+
+        Ok(remote_hash.is_some_and(|hash| hash.eq_ignore_ascii_case(&local_hash)))
+    }
+
+    /// Upload the file at `local_path` to `file_path` on the current [`Neocities`] site,
+    /// unless the file already there has the same SHA-1 hash. See [`Neocities::verify_file`]
+    pub async fn upload_if_changed<P: AsRef<Path>>(
+        &self,
+        file_path: String,
+        local_path: P,
+    ) -> Result<UploadOutcome, NeocitiesError> {
+        let local_path = local_path.as_ref();
+
+        if self.verify_file(&file_path, local_path).await? {
+            return Ok(UploadOutcome::Unchanged);
+        }
+
+        self.upload(file_path, std::fs::read(local_path)?).await?;
+        Ok(UploadOutcome::Uploaded)
+    }
+
+    /// Upload the file at `local_path` to `file_path` on the current [`Neocities`] site, unless
+    /// the file already there was updated after the local file was last modified. This avoids
+    /// hashing files, unlike [`Neocities::upload_if_changed`], but only looks at timestamps.
+    ///
+    /// The local modification time is compared to the server's clock, so skew between the two
+    /// can cause files to be skipped or uploaded again. The local file only counts as newer if
+    /// it is more than `tolerance` ahead of the remote file. Files missing from the site or with
+    /// a timestamp that can't be parsed are always uploaded
+    pub async fn upload_if_newer<P: AsRef<Path>>(
+        &self,
+        file_path: String,
+        local_path: P,
+        tolerance: Duration,
+    ) -> Result<UploadOutcome, NeocitiesError> {
+        let local_path = local_path.as_ref();
+        let modified: DateTime<Utc> = std::fs::metadata(local_path)?.modified()?.into();
+
+        let remote = self
+            .get_metadata(&file_path)
+            .await?
+            .and_then(|entry| entry.updated_at_utc());
+
+        if let Some(remote) = remote {
+            let tolerance = chrono::Duration::from_std(tolerance).unwrap_or(chrono::Duration::MAX);
+
+            if modified
+                <= remote
+                    .checked_add_signed(tolerance)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC)
+            {
+                return Ok(UploadOutcome::Unchanged);
+            }
+        }
+
+        self.upload(file_path, std::fs::read(local_path)?).await?;
+        Ok(UploadOutcome::Uploaded)
+    }
+
+    /// Send a GET request to an API endpoint and return the JSON response as is, including any
+    /// fields the typed methods drop. `endpoint` is relative to the API root and can include a
+    /// query string, e.g. `"info?sitename=example"`. Error responses are still returned as
+    /// [`NeocitiesError::ApiErr`]
+    pub async fn get_raw(&self, endpoint: &str) -> Result<serde_json::Value, NeocitiesError> {
+        let request = self.request(Method::GET, endpoint);
+
+        let response = self.send(request).await?;
+        let value = self.read_json::<serde_json::Value>(response).await?;
+
+        if value["result"] == "error" {
+            let field = |name: &str| value[name].as_str().unwrap_or_default().to_string();
+            return Err(NeocitiesError::ApiErr(
+                field("error_type"),
+                field("message"),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// Create a request to an API endpoint with authentication already applied, for endpoints
+    /// this crate doesn't wrap yet. `endpoint` is relative to the API root, e.g. `"info"`.
+    ///
+    /// This is a lower level escape hatch. The request is sent by the caller, so it
+    /// bypasses the rate limiting done by [`Neocities`] and the response is not checked
+    pub fn raw_request(&self, method: Method, endpoint: &str) -> RequestBuilder {
+        self.request(method, endpoint)
+    }
+
+    /// Check that the client is set up correctly before doing anything else with it, such as
+    /// when a tool starts up.
+    ///
+    /// The API base URL set with [`NeocitiesBuilder::base_url`] and any site URL set with
+    /// [`NeocitiesBuilder::site_url`] must be valid `http` or `https` URLs, otherwise
+    /// [`NeocitiesError::InvalidConfig`] is returned without sending anything.
+    /// Then a single [`Neocities::info`] request is sent for the authorized site, so an
+    /// unreachable server fails with [`NeocitiesError::ReqwestErr`] and bad credentials fail
+    /// with [`ApiErrorKind::InvalidAuth`]
+    pub async fn check_config(&self) -> Result<(), NeocitiesError> {
+        check_url("base URL", &self.api_url)?;
+
+        if let Some(site_url) = &self.site_url {
+            check_url("site URL", site_url)?;
+        }
+
+        self.info("").await.map(|_| ())
+    }
+
+    /// The rate limit reported by the server on the last response, if it sent one.
+    ///
+    /// When the server reports that no requests remain, further requests wait until the
+    /// reported reset time instead of being sent and rejected. If the server does not send
+    /// rate limit headers this is `None` and requests are never delayed
+    pub fn rate_limit_status(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    fn request(&self, method: Method, endpoint: &str) -> RequestBuilder {
+        let request = self.client.request(
+            method,
+            self.api_url.clone() + endpoint.trim_start_matches('/'),
+        );
+
+        add_authorization_header(request, &self.auth)
+    }
+
+    // Every request goes through here so they all share the rate limit
+    async fn send(&self, request: RequestBuilder) -> Result<Response, NeocitiesError> {
+        if let Some(governor) = &self.governor {
+            governor.acquire().await;
+        }
+
+        if let Some(wait) = self.rate_limit_status().and_then(|limit| limit.wait()) {
+            tokio::time::sleep(wait).await;
+        }
+
+        let response = match &self.before_send {
+            Some(hook) => {
+                let mut request = request.build()?;
+                let auth = request.headers().get(AUTHORIZATION).cloned();
+
+                hook(&mut request);
+
+                // The hook may not change or remove the credentials
+                if let Some(auth) = auth {
+                    request.headers_mut().insert(AUTHORIZATION, auth);
+                }
+
+                self.client.execute(request).await
+            }
+            None => request.send().await,
+        }
+        .map_err(|e| self.map_timeout(e))?;
+
+        if let Some(hook) = &self.after_receive {
+            hook(&response);
+        }
+
+        if let Some(limit) = RateLimit::from_headers(response.headers()) {
+            *self.rate_limit.lock().unwrap() = Some(limit);
+        }
+
+        Ok(response)
+    }
+
+    // Read the whole body of `response`, failing as soon as it goes over `max_response_bytes`
+    async fn read_body(&self, response: Response) -> Result<Vec<u8>, NeocitiesError> {
+        self.read_body_with_progress(response, &mut |_, _| {}).await
+    }
+
+    // Like `read_body`, calling `progress` with the bytes received so far and the expected total
+    async fn read_body_with_progress(
+        &self,
+        mut response: Response,
+        progress: &mut (dyn FnMut(u64, Option<u64>) + Send),
+    ) -> Result<Vec<u8>, NeocitiesError> {
+        let total = response.content_length();
+        let limit = self.max_response_bytes;
+
+        if total.is_some_and(|len| len > limit) {
+            return Err(NeocitiesError::ResponseTooLarge(limit));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| self.map_timeout(e))? {
+            if (body.len() + chunk.len()) as u64 > limit {
+                return Err(NeocitiesError::ResponseTooLarge(limit));
+            }
+            body.extend_from_slice(&chunk);
+            progress(body.len() as u64, total);
+        }
+
+        Ok(body)
+    }
+
+    async fn read_json<T: DeserializeOwned>(
+        &self,
+        response: Response,
+    ) -> Result<T, NeocitiesError> {
+        Ok(serde_json::from_slice(&self.read_body(response).await?)?)
     }
+
+    // Report requests that ran over the timeout set on the builder as `Timeout`
+    fn map_timeout(&self, error: reqwest::Error) -> NeocitiesError {
+        match self.timeout {
+            Some(timeout) if error.is_timeout() => NeocitiesError::Timeout(timeout),
+            _ => NeocitiesError::ReqwestErr(error),
+        }
+    }
+
+    // A form part holding `file`, throttled to the upload rate limit if there is one
+    fn file_part<T: Into<Body>>(&self, file: T) -> Part {
+        let body = file.into();
+
+        match (self.upload_rate_limit, body.as_bytes()) {
+            (Some(bytes_per_sec), Some(bytes)) => {
+                let len = bytes.len() as u64;
+                Part::stream_with_length(throttle(bytes.to_vec(), bytes_per_sec), len)
+            }
+            _ => Part::stream(body),
+        }
+    }
+
+    // Upload a form where each part is a file keyed by its path on the site
+    async fn upload_form(&self, form: Form) -> Result<String, NeocitiesError> {
+        let mut request = self.request(Method::POST, "upload");
+        request = request.multipart(form);
+
+        let response = self.send(request).await?;
+
+        self.read_json::<ApiResult<String>>(response)
+            .await?
+            .into_result()
+    }
+
+    // The public URL of `path` on the site, which must already be validated
+    async fn public_url(&self, path: &str) -> Result<String, NeocitiesError> {
+        match &self.site_url {
+            Some(site_url) => Ok(format!("{}{}", site_url, path)),
+            None => Ok(format!(
+                "https://{}.neocities.org/{}",
+                self.site_name().await?,
+                path
+            )),
+        }
+    }
+
+    async fn site_name(&self) -> Result<&str, NeocitiesError> {
+        if let Some(name) = self.site_name.get() {
+            return Ok(name);
+        }
+
+        let info = self.info("").await?;
+        Ok(self.site_name.get_or_init(|| info.site_name))
+    }
+}
+
+// Check that a path refers to a file or directory on the site rather than the root,
+// returning it without leading or trailing slashes
+fn validate_path(path: &str) -> Result<&str, NeocitiesError> {
+    let path = path.trim_matches('/');
+
+    if path.is_empty() {
+        return Err(NeocitiesError::InvalidInput(
+            "path must not be empty or the site root".to_string(),
+        ));
+    }
+
+    if path
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return Err(NeocitiesError::InvalidInput(format!(
+            "path `{}` must not contain `.` or `..`",
+            path
+        )));
+    }
+
+    Ok(path)
+}
+
+// Whether the extension of `path` is one of `allowed`, ignoring case
+fn extension_allowed(allowed: &[&str], path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| allowed.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str()))
+}
+
+// Check that no path appears more than once in an upload, ignoring leading and trailing slashes
+fn check_duplicates<'a, I: IntoIterator<Item = &'a str>>(paths: I) -> Result<(), NeocitiesError> {
+    let mut seen = HashSet::new();
+
+    for path in paths {
+        let path = path.trim_matches('/');
+
+        if !seen.insert(path) {
+            return Err(NeocitiesError::InvalidInput(format!(
+                "path `{}` appears more than once in the upload",
+                path
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Check that a configured URL is an http or https URL, `name` describes it in the error
+fn check_url(name: &str, url: &str) -> Result<(), NeocitiesError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| NeocitiesError::InvalidConfig(format!("invalid {} `{}`: {}", name, url, e)))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(NeocitiesError::InvalidConfig(format!(
+            "{} `{}` must use http or https",
+            name, url
+        )));
+    }
+
+    Ok(())
+}
+
+// Site names can only contain letters, numbers, hyphens and underscores
+fn validate_site_name(site_name: &str) -> Result<(), NeocitiesError> {
+    let valid = !site_name.is_empty()
+        && site_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(NeocitiesError::InvalidInput(format!(
+            "`{}` is not a valid site name",
+            site_name
+        )));
+    }
+
+    Ok(())
+}
+
+// Hex encoded SHA-1, matching the format of `ListEntry::File::sha1_hash`
+fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(bytes))
+}
+
+fn sha1_file<P: AsRef<Path>>(path: P) -> Result<String, NeocitiesError> {
+    let mut hasher = Sha1::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 fn add_authorization_header(request: RequestBuilder, auth: &Auth) -> RequestBuilder {
@@ -199,11 +1580,209 @@ fn add_authorization_header(request: RequestBuilder, auth: &Auth) -> RequestBuil
     }
 }
 
+/// The kind of error reported by the API, parsed from the `error_type` of a [`NeocitiesError::ApiErr`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiErrorKind {
+    /// A path passed to a delete does not exist on the site
+    MissingFiles,
+    /// The API key or username and password were rejected.
+    ///
+    /// The API reports a revoked or replaced key exactly like a mistyped one, so there is no
+    /// way to tell them apart. When a key that used to work starts failing with this, it has
+    /// most likely been replaced by a new one from [`Neocities::key`] or the site settings
+    InvalidAuth,
+    /// The requested site does not exist
+    SiteNotFound,
+    /// The site is not allowed to upload files of this type
+    InvalidFileType,
+    /// Any other error type
+    Other(String),
+}
+
+impl ApiErrorKind {
+    fn from_error_type(error_type: &str) -> Self {
+        match error_type {
+            "missing_files" => ApiErrorKind::MissingFiles,
+            "invalid_auth" => ApiErrorKind::InvalidAuth,
+            "site_not_found" => ApiErrorKind::SiteNotFound,
+            "invalid_file_type" => ApiErrorKind::InvalidFileType,
+            other => ApiErrorKind::Other(other.to_string()),
+        }
+    }
+}
+
 /// The `neocities` error type.
+///
+/// Some variants only exist with certain features enabled and more may be added,
+/// so matches on it need a wildcard arm
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum NeocitiesError {
     #[error("API returned error `{0}` with message `{1}`")]
     ApiErr(String, String),
     #[error(transparent)]
     ReqwestErr(#[from] reqwest::Error),
+    #[error(transparent)]
+    IoErr(#[from] io::Error),
+    #[error("files do not exist on the site: {0:?}")]
+    MissingFiles(Vec<String>),
+    #[error("no credentials found, checked environment variables: {}", .0.join(", "))]
+    MissingCredentials(Vec<String>),
+    #[error("hash of `{path}` on the site is {actual:?}, expected `{expected}`")]
+    HashMismatch {
+        path: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("operation was cancelled")]
+    Cancelled,
+    #[error("timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("response body is larger than the limit of {0} bytes")]
+    ResponseTooLarge(u64),
+    #[error(transparent)]
+    JsonErr(#[from] serde_json::Error),
+    #[cfg(feature = "watch")]
+    #[error(transparent)]
+    WatchErr(#[from] notify::Error),
+    #[cfg(feature = "zip")]
+    #[error(transparent)]
+    ZipErr(#[from] zip::result::ZipError),
+}
+
+impl NeocitiesError {
+    /// The kind of API error, if this error was returned by the API
+    pub fn api_error_kind(&self) -> Option<ApiErrorKind> {
+        match self {
+            NeocitiesError::ApiErr(error_type, _) => {
+                Some(ApiErrorKind::from_error_type(error_type))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse<T: serde::de::DeserializeOwned>(
+        value: serde_json::Value,
+    ) -> Result<T, NeocitiesError> {
+        serde_json::from_value::<ApiResult<T>>(value)?.into_result()
+    }
+
+    fn file(path: &str) -> ListEntry {
+        ListEntry::File {
+            path: path.to_string(),
+            size: 0,
+            updated_at: String::new(),
+            sha1_hash: String::new(),
+        }
+    }
+
+    fn paths(page: &ListPage) -> Vec<&str> {
+        page.entries.iter().map(ListEntry::path).collect()
+    }
+
+    #[test]
+    fn list_page_sorts_then_pages() {
+        let entries = || {
+            vec!["c.html", "a.html", "e.html", "b.html", "d.html"]
+                .into_iter()
+                .map(file)
+                .collect()
+        };
+
+        let first = ListPage::from_entries(entries(), 0, 2);
+        assert_eq!(paths(&first), ["a.html", "b.html"]);
+        assert_eq!(first.total, 5);
+        assert!(first.has_more);
+
+        let last = ListPage::from_entries(entries(), 4, 2);
+        assert_eq!(paths(&last), ["e.html"]);
+        assert!(!last.has_more);
+    }
+
+    #[test]
+    fn list_page_past_the_end_is_empty() {
+        let page = ListPage::from_entries(vec![file("a.html")], usize::MAX, usize::MAX);
+
+        assert!(page.entries.is_empty());
+        assert_eq!(page.total, 1);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn api_result_reads_every_data_field() {
+        let key: String = parse(json!({ "result": "success", "api_key": "abc" })).unwrap();
+        assert_eq!(key, "abc");
+
+        let message: String = parse(json!({ "result": "success", "message": "ok" })).unwrap();
+        assert_eq!(message, "ok");
+
+        let files: Vec<ListEntry> = parse(json!({ "result": "success", "files": [] })).unwrap();
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn api_result_treats_a_missing_listing_as_empty() {
+        let files: Option<Vec<ListEntry>> = parse(json!({ "result": "success" })).unwrap();
+        assert!(files.is_none());
+    }
+
+    #[test]
+    fn api_result_turns_errors_into_api_errors() {
+        let error = parse::<String>(json!({
+            "result": "error",
+            "error_type": "invalid_auth",
+            "message": "invalid credentials",
+        }))
+        .unwrap_err();
+        assert_eq!(error.api_error_kind(), Some(ApiErrorKind::InvalidAuth));
+
+        // Errors missing their details still count as API errors
+        let error = parse::<String>(json!({ "result": "error" })).unwrap_err();
+        assert!(matches!(error, NeocitiesError::ApiErr(kind, _) if kind.is_empty()));
+    }
+
+    #[test]
+    fn validate_path_trims_slashes() {
+        assert_eq!(validate_path("/images/cat.png").unwrap(), "images/cat.png");
+        assert_eq!(validate_path("images/").unwrap(), "images");
+        assert_eq!(validate_path("a..b/.hidden").unwrap(), "a..b/.hidden");
+    }
+
+    #[test]
+    fn validate_path_rejects_the_root_and_dot_segments() {
+        for path in [
+            "",
+            "/",
+            "//",
+            "../secret",
+            "images/../index.html",
+            "./index.html",
+            "a/./b",
+        ] {
+            assert!(
+                matches!(validate_path(path), Err(NeocitiesError::InvalidInput(_))),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn api_result_rejects_unknown_results() {
+        assert!(parse::<String>(json!({ "result": "maybe", "message": "?" })).is_err());
+    }
 }
@@ -0,0 +1,85 @@
+use reqwest::Body;
+
+use crate::{Auth, Neocities, NeocitiesError};
+
+/// A group of [`Neocities`] clients for managing several sites at once,
+/// each authenticated with its own API key.
+///
+/// Every client shares a single connection pool. Sites are addressed by the
+/// index of their key in the list passed to [`Neocities::from_keys`]
+pub struct MultiSite {
+    sites: Vec<Neocities>,
+}
+
+impl Neocities {
+    /// Create a [`MultiSite`] with a client for each API key in `keys`
+    pub fn from_keys(keys: Vec<String>) -> MultiSite {
+        let client = reqwest::Client::new();
+
+        MultiSite {
+            sites: keys
+                .into_iter()
+                .map(|key| Neocities::with_client(Auth::Key(key), client.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl MultiSite {
+    /// Get the client for the site at `site_index`
+    pub fn site(&self, site_index: usize) -> Option<&Neocities> {
+        self.sites.get(site_index)
+    }
+
+    /// Get the clients for every site
+    pub fn sites(&self) -> &[Neocities] {
+        &self.sites
+    }
+
+    /// The number of sites
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    /// Whether there are no sites
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    /// Upload a file to the site at `site_index`.
+    /// Returns the success message sent by the server, or [`NeocitiesError::InvalidInput`]
+    /// if there is no site at `site_index`
+    pub async fn upload_to<T: Into<Body>>(
+        &self,
+        site_index: usize,
+        file_path: String,
+        file: T,
+    ) -> Result<String, NeocitiesError> {
+        let site = self.site(site_index).ok_or_else(|| {
+            NeocitiesError::InvalidInput(format!(
+                "no site at index {}, there are {} sites",
+                site_index,
+                self.len()
+            ))
+        })?;
+
+        site.upload(file_path, file).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn upload_to_a_missing_site_is_an_error() {
+        let sites = Neocities::from_keys(vec!["key".to_string()]);
+
+        let err = sites
+            .upload_to(1, "index.html".to_string(), "hello")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, NeocitiesError::InvalidInput(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use crate::{ListEntry, Neocities, NeocitiesError};
+
+// Pages the server shows on its own without anything linking to them
+const SPECIAL_PAGES: &[&str] = &["index.html", "not_found.html"];
+
+impl Neocities {
+    /// Find the files on the current [`Neocities`] site that no HTML page on the site links to,
+    /// such as leftover images from an old design. Returns their paths, sorted.
+    ///
+    /// Every `.html` and `.htm` file is downloaded and searched for `href`, `src`, `srcset` and
+    /// `poster` attributes. Relative links, links starting with `/` and full links to the site
+    /// or its custom domain are all followed, and links to a directory or to a page without its
+    /// `.html` extension count just like the server treats them. The root `index.html` and
+    /// `not_found.html` are never reported.
+    ///
+    /// Only HTML is searched, so files only used from CSS or JavaScript, like fonts or background
+    /// images, are reported as orphans too. Check the results before deleting anything
+    pub async fn find_orphans(&self) -> Result<Vec<String>, NeocitiesError> {
+        let info = self.info("").await?;
+        let mut hosts = vec![format!("{}.neocities.org", info.site_name)];
+        hosts.extend(info.domain);
+
+        let files: HashSet<String> = self
+            .list("")
+            .await?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                ListEntry::File { path, .. } => Some(path),
+                ListEntry::Directory { .. } => None,
+            })
+            .collect();
+        let mut linked = HashSet::new();
+
+        for page in files.iter().filter(|path| is_page(path)) {
+            let html = String::from_utf8_lossy(&self.download(page).await?).into_owned();
+            let base = Url::parse(&format!("https://{}/", hosts[0]))
+                .and_then(|root| root.join(page))
+                .map_err(|e| NeocitiesError::InvalidInput(e.to_string()))?;
+
+            for link in links(&html) {
+                if let Some(path) = resolve(&base, &link, &hosts, &files) {
+                    linked.insert(path);
+                }
+            }
+        }
+
+        let mut orphans: Vec<String> = files
+            .into_iter()
+            .filter(|path| !linked.contains(path) && !SPECIAL_PAGES.contains(&path.as_str()))
+            .collect();
+        orphans.sort();
+
+        Ok(orphans)
+    }
+}
+
+fn is_page(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+// Every URL a page links to. Kept out of async code since `Html` is not `Send`
+fn links(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("[href], [src], [srcset], [poster]").unwrap();
+    let mut links = Vec::new();
+
+    for element in document.select(&selector) {
+        let element = element.value();
+
+        for name in ["href", "src", "poster"] {
+            links.extend(element.attr(name).map(str::to_string));
+        }
+
+        // Each candidate in a srcset is a URL optionally followed by a size
+        if let Some(srcset) = element.attr("srcset") {
+            links.extend(
+                srcset
+                    .split(',')
+                    .filter_map(|candidate| candidate.split_whitespace().next())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    links
+}
+
+// The path of the file on the site a link from the page at `base` points to, if any
+fn resolve(base: &Url, link: &str, hosts: &[String], files: &HashSet<String>) -> Option<String> {
+    let url = base.join(link.trim()).ok()?;
+
+    if !matches!(url.scheme(), "http" | "https")
+        || !hosts.iter().any(|host| url.host_str() == Some(host))
+    {
+        return None;
+    }
+
+    let path = percent_decode_str(url.path().trim_start_matches('/'))
+        .decode_utf8_lossy()
+        .into_owned();
+
+    // The server serves `dir/index.html` for `dir/` and `page.html` for `page`
+    let candidates = if path.is_empty() || path.ends_with('/') {
+        vec![format!("{}index.html", path)]
+    } else {
+        vec![
+            path.clone(),
+            format!("{}.html", path),
+            format!("{}/index.html", path),
+        ]
+    };
+
+    candidates.into_iter().find(|path| files.contains(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILES: &[&str] = &[
+        "index.html",
+        "about.html",
+        "blog/index.html",
+        "blog/post.html",
+        "images/cat.png",
+        "my photo.jpg",
+    ];
+
+    // Resolve `link` from the page at `page` on `test.neocities.org`
+    fn resolve_from(page: &str, link: &str) -> Option<String> {
+        let base = Url::parse("https://test.neocities.org/")
+            .unwrap()
+            .join(page)
+            .unwrap();
+        let hosts = vec!["test.neocities.org".to_string(), "example.com".to_string()];
+        let files = FILES.iter().map(|path| path.to_string()).collect();
+
+        resolve(&base, link, &hosts, &files)
+    }
+
+    #[test]
+    fn relative_links_resolve_against_the_page() {
+        assert_eq!(
+            resolve_from("blog/post.html", "index.html").as_deref(),
+            Some("blog/index.html")
+        );
+        assert_eq!(
+            resolve_from("blog/post.html", "../about.html").as_deref(),
+            Some("about.html")
+        );
+        assert_eq!(
+            resolve_from("blog/post.html", "./../images/cat.png").as_deref(),
+            Some("images/cat.png")
+        );
+        assert_eq!(
+            resolve_from("index.html", " my%20photo.jpg ").as_deref(),
+            Some("my photo.jpg")
+        );
+    }
+
+    #[test]
+    fn links_from_the_root_ignore_the_page() {
+        assert_eq!(
+            resolve_from("blog/post.html", "/").as_deref(),
+            Some("index.html")
+        );
+        assert_eq!(
+            resolve_from("blog/post.html", "/about.html").as_deref(),
+            Some("about.html")
+        );
+    }
+
+    #[test]
+    fn absolute_links_only_resolve_on_the_site_hosts() {
+        assert_eq!(
+            resolve_from("index.html", "https://test.neocities.org/about.html").as_deref(),
+            Some("about.html")
+        );
+        assert_eq!(
+            resolve_from("index.html", "http://example.com/images/cat.png").as_deref(),
+            Some("images/cat.png")
+        );
+        assert_eq!(
+            resolve_from("index.html", "https://other.neocities.org/about.html"),
+            None
+        );
+        assert_eq!(resolve_from("index.html", "mailto:me@example.com"), None);
+    }
+
+    #[test]
+    fn directories_resolve_to_their_index() {
+        assert_eq!(
+            resolve_from("index.html", "blog/").as_deref(),
+            Some("blog/index.html")
+        );
+        assert_eq!(
+            resolve_from("index.html", "/blog").as_deref(),
+            Some("blog/index.html")
+        );
+    }
+
+    #[test]
+    fn extensionless_links_resolve_to_html_pages() {
+        assert_eq!(
+            resolve_from("index.html", "about").as_deref(),
+            Some("about.html")
+        );
+        assert_eq!(
+            resolve_from("index.html", "blog/post").as_deref(),
+            Some("blog/post.html")
+        );
+        assert_eq!(resolve_from("index.html", "missing"), None);
+    }
+
+    #[test]
+    fn queries_and_fragments_are_ignored() {
+        assert_eq!(
+            resolve_from("index.html", "about.html?ref=home").as_deref(),
+            Some("about.html")
+        );
+        assert_eq!(
+            resolve_from("index.html", "about#team").as_deref(),
+            Some("about.html")
+        );
+        assert_eq!(
+            resolve_from("about.html", "#top").as_deref(),
+            Some("about.html")
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::{stream, Stream};
+use reqwest::{header::HeaderMap, Body};
+use tokio::time::{sleep, sleep_until, Instant};
+
+/// The rate limit reported by the server in the headers of its last response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Total requests allowed in the current window, from `X-RateLimit-Limit`
+    pub limit: Option<u64>,
+    /// Requests left in the current window, from `X-RateLimit-Remaining`
+    pub remaining: u64,
+    /// When the current window ends, from `X-RateLimit-Reset`
+    pub reset: Option<SystemTime>,
+}
+
+impl RateLimit {
+    // `X-RateLimit-Reset` is either a unix timestamp or a number of seconds from now,
+    // anything too large to be a sensible delay is treated as a timestamp
+    const MAX_RESET_DELAY: u64 = 1_000_000_000;
+
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        let reset = header("x-ratelimit-reset").map(|reset| {
+            if reset > Self::MAX_RESET_DELAY {
+                UNIX_EPOCH + Duration::from_secs(reset)
+            } else {
+                SystemTime::now() + Duration::from_secs(reset)
+            }
+        });
+
+        Some(Self {
+            limit: header("x-ratelimit-limit"),
+            remaining: header("x-ratelimit-remaining")?,
+            reset,
+        })
+    }
+
+    // How long to wait before sending another request without going over the limit
+    pub(crate) fn wait(&self) -> Option<Duration> {
+        if self.remaining > 0 {
+            return None;
+        }
+
+        self.reset?.duration_since(SystemTime::now()).ok()
+    }
+}
+
+// A token bucket shared by every request sent through one client, so the
+// combined request rate of all in-flight operations stays under the ceiling
+pub(crate) struct RequestGovernor {
+    capacity: f64,
+    // Time it takes to refill a single token
+    refill: Duration,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RequestGovernor {
+    pub(crate) fn new(requests: u32, per: Duration) -> Self {
+        let requests = requests.max(1);
+
+        Self {
+            capacity: requests as f64,
+            refill: per / requests,
+            bucket: Mutex::new(Bucket {
+                tokens: requests as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Wait until a request is allowed to be sent
+    pub(crate) async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+
+                let refilled = now.duration_since(bucket.refilled_at).as_secs_f64()
+                    / self.refill.as_secs_f64();
+                bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                self.refill.mul_f64(1.0 - bucket.tokens)
+            };
+
+            sleep(wait).await;
+        }
+    }
+}
+
+// Slices of a second each throttled chunk of a body is sent over
+const THROTTLE_CHUNKS_PER_SEC: u64 = 10;
+
+// Turn `data` into a body sent at no more than `bytes_per_sec` on average,
+// in chunks of a tenth of that with pauses in between
+pub(crate) fn throttle(data: Vec<u8>, bytes_per_sec: u64) -> Body {
+    Body::wrap_stream(throttled_chunks(data, bytes_per_sec))
+}
+
+fn throttled_chunks(
+    data: Vec<u8>,
+    bytes_per_sec: u64,
+) -> impl Stream<Item = std::io::Result<Vec<u8>>> {
+    let bytes_per_sec = bytes_per_sec.max(1);
+    let chunk_size = (bytes_per_sec / THROTTLE_CHUNKS_PER_SEC).max(1) as usize;
+    let data = Arc::new(data);
+
+    // The clock starts when the first chunk is asked for rather than when the body is made,
+    // so time spent waiting to send the request doesn't let the whole body out in a burst
+    stream::unfold(
+        (0, None),
+        move |(offset, started): (usize, Option<Instant>)| {
+            let data = data.clone();
+
+            async move {
+                if offset >= data.len() {
+                    return None;
+                }
+
+                let started = started.unwrap_or_else(Instant::now);
+
+                // Wait until sending everything so far would be within the limit
+                sleep_until(
+                    started + Duration::from_secs_f64(offset as f64 / bytes_per_sec as f64),
+                )
+                .await;
+
+                let end = (offset + chunk_size).min(data.len());
+                Some((Ok(data[offset..end].to_vec()), (end, Some(started))))
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(values: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn rate_limit_reads_headers() {
+        let limit = RateLimit::from_headers(&headers(&[
+            ("x-ratelimit-limit", "60"),
+            ("x-ratelimit-remaining", " 12 "),
+            ("x-ratelimit-reset", "1700000000"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            limit,
+            RateLimit {
+                limit: Some(60),
+                remaining: 12,
+                reset: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            }
+        );
+        assert_eq!(limit.wait(), None);
+    }
+
+    #[test]
+    fn rate_limit_reset_can_be_seconds_from_now() {
+        let limit = RateLimit::from_headers(&headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "30"),
+        ]))
+        .unwrap();
+
+        let wait = limit.wait().unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn rate_limit_needs_the_remaining_header() {
+        assert_eq!(
+            RateLimit::from_headers(&headers(&[("x-ratelimit-limit", "60")])),
+            None
+        );
+        assert_eq!(
+            RateLimit::from_headers(&headers(&[("x-ratelimit-remaining", "many")])),
+            None
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn governor_caps_request_rate() {
+        let governor = RequestGovernor::new(2, Duration::from_secs(1));
+        let start = Instant::now();
+
+        // The first two go out in a burst, the rest at one every half second
+        for _ in 0..6 {
+            governor.acquire().await;
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(2), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(2100), "{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn governor_allows_a_burst_after_being_idle() {
+        let governor = RequestGovernor::new(3, Duration::from_secs(3));
+        sleep(Duration::from_secs(10)).await;
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            governor.acquire().await;
+        }
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_starts_counting_when_the_body_is_first_read() {
+        use futures_util::StreamExt;
+
+        let chunks = throttled_chunks(vec![0; 1000], 100);
+        // As if the request sat behind others before being sent
+        sleep(Duration::from_secs(30)).await;
+        let start = Instant::now();
+
+        let sent: Vec<Vec<u8>> = chunks.map(Result::unwrap).collect().await;
+
+        assert_eq!(sent.concat().len(), 1000);
+        // The last of the 100 chunks of 10 bytes goes out 9.9 seconds after the first
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(9900), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(10), "{:?}", elapsed);
+    }
+}
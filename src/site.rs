@@ -0,0 +1,33 @@
+use crate::{validate_site_name, Info, Neocities, NeocitiesError};
+
+/// A view of a single Neocities site that remembers its name between calls.
+///
+/// Created with [`Neocities::for_site`], borrowing the client and its authentication
+pub struct SiteView<'a> {
+    neocities: &'a Neocities,
+    site_name: String,
+}
+
+impl Neocities {
+    /// Get a view of the site named `site_name` for repeated queries about it
+    pub fn for_site<T: Into<String>>(&self, site_name: T) -> SiteView<'_> {
+        SiteView {
+            neocities: self,
+            site_name: site_name.into(),
+        }
+    }
+}
+
+impl SiteView<'_> {
+    /// The name of the site being viewed
+    pub fn site_name(&self) -> &str {
+        &self.site_name
+    }
+
+    /// Get info about the site, see [`Neocities::info`].
+    /// Returns [`NeocitiesError::InvalidInput`] if the site name is empty or malformed
+    pub async fn info(&self) -> Result<Info, NeocitiesError> {
+        validate_site_name(&self.site_name)?;
+        self.neocities.info(&self.site_name).await
+    }
+}
@@ -0,0 +1,896 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{
+    check_duplicates, sha1_file, validate_path, ListEntry, Neocities, NeocitiesError, SymlinkPolicy,
+};
+
+/// The differences between two sets of files, such as a local directory and a site.
+///
+/// Files are compared by SHA-1 hash and directories by presence only. Paths are sorted
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ListingDiff {
+    /// Paths only present in the new set
+    pub added: Vec<String>,
+    /// Paths only present in the old set
+    pub removed: Vec<String>,
+    /// Paths present in both sets whose contents differ
+    pub modified: Vec<String>,
+}
+
+impl ListingDiff {
+    /// Whether both sets of files are identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl ListEntry {
+    /// Compare two site listings, such as a staging site and a production site.
+    /// `added` holds paths only in `new`, `removed` holds paths only in `old`
+    /// and `modified` holds files whose SHA-1 hashes differ
+    pub fn diff(old: &[ListEntry], new: &[ListEntry]) -> ListingDiff {
+        diff_listings(&index(old), &index(new))
+    }
+}
+
+/// The changes made to a site by a deploy.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeployReport {
+    /// Paths of files that were uploaded
+    pub uploaded: Vec<String>,
+    /// Paths of files that were deleted from the site
+    pub deleted: Vec<String>,
+}
+
+impl DeployReport {
+    /// Split this report by category, such as `assets` and `pages`, for summarizing large deploys.
+    /// `categories` maps path prefixes like `images/` to category names, and each path goes in the
+    /// category of the longest prefix it starts with. Paths matching no prefix go in the category `""`
+    pub fn by_category(
+        &self,
+        categories: &HashMap<String, String>,
+    ) -> BTreeMap<String, DeployReport> {
+        let category = |path: &str| {
+            categories
+                .iter()
+                .filter(|(prefix, _)| path.starts_with(prefix.trim_start_matches('/')))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map_or("", |(_, category)| category.as_str())
+                .to_string()
+        };
+        let mut reports: BTreeMap<String, DeployReport> = BTreeMap::new();
+
+        for path in &self.uploaded {
+            reports
+                .entry(category(path))
+                .or_default()
+                .uploaded
+                .push(path.clone());
+        }
+        for path in &self.deleted {
+            reports
+                .entry(category(path))
+                .or_default()
+                .deleted
+                .push(path.clone());
+        }
+
+        reports
+    }
+}
+
+/// Progress of a deploy made with [`Neocities::resume_deploy`], which can be saved
+/// and loaded between runs to pick up an interrupted deploy where it stopped.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployState {
+    /// Hex encoded SHA-1 hash of each file confirmed to be on the site, by path
+    pub uploaded: BTreeMap<String, String>,
+}
+
+/// A file whose contents differ between a local directory and a site, from [`Neocities::verify_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMismatch {
+    /// Path of the file relative to the local root and the site
+    pub path: String,
+    /// Hex encoded SHA-1 hash of the local file, `None` if it only exists on the site
+    pub local_sha1: Option<String>,
+    /// Hex encoded SHA-1 hash reported by the site, `None` if it only exists locally
+    pub remote_sha1: Option<String>,
+}
+
+/// The result of checking a site against a local directory with [`Neocities::verify_against`].
+///
+/// Only files are compared, directories are ignored. Paths are sorted
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Files on both sides whose contents differ
+    pub mismatched: Vec<String>,
+    /// Local files missing from the site
+    pub missing_remote: Vec<String>,
+    /// Files on the site that don't exist locally
+    pub extra_remote: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether the site exactly matches the local directory
+    pub fn is_clean(&self) -> bool {
+        self.mismatched.is_empty() && self.missing_remote.is_empty() && self.extra_remote.is_empty()
+    }
+}
+
+/// A snapshot of a local directory with the SHA-1 hash and size of every file.
+///
+/// Building a manifest reads every file under the root once. It can then be passed to
+/// [`Neocities::plan`] and [`Neocities::apply`] or compared with [`LocalManifest::diff`]
+/// without hashing the directory again. Paths are relative to the root and use `/` as the separator
+#[derive(Debug, Clone)]
+pub struct LocalManifest {
+    root: PathBuf,
+    files: BTreeMap<String, LocalFile>,
+    directories: BTreeSet<String>,
+}
+
+/// A file in a [`LocalManifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalFile {
+    /// Hex encoded SHA-1 hash of the file
+    pub sha1_hash: String,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+impl LocalManifest {
+    /// Walk the directory `root`, hashing every file under it. Symbolic links are left out,
+    /// use [`LocalManifest::build_with_symlinks`] to follow them instead
+    pub fn build<P: AsRef<Path>>(root: P) -> Result<Self, NeocitiesError> {
+        Self::build_with_symlinks(root, SymlinkPolicy::default())
+    }
+
+    /// Like [`LocalManifest::build`], treating symbolic links under `root` as `symlinks` says.
+    /// Followed links are recorded with the hash and size of what they point to
+    pub fn build_with_symlinks<P: AsRef<Path>>(
+        root: P,
+        symlinks: SymlinkPolicy,
+    ) -> Result<Self, NeocitiesError> {
+        let root = root.as_ref();
+        let mut files = BTreeMap::new();
+        let mut directories = BTreeSet::new();
+
+        for entry in symlinks.walk(WalkDir::new(root).min_depth(1)) {
+            let entry = entry?;
+            let path = remote_path(root, entry.path());
+
+            if entry.file_type().is_dir() {
+                directories.insert(path);
+            } else {
+                let file = LocalFile {
+                    sha1_hash: sha1_file(entry.path())?,
+                    size: std::fs::metadata(entry.path())?.len(),
+                };
+                files.insert(path, file);
+            }
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+            directories,
+        })
+    }
+
+    /// The directory this manifest was built from
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every file in the manifest, by path
+    pub fn files(&self) -> &BTreeMap<String, LocalFile> {
+        &self.files
+    }
+
+    /// Every directory in the manifest
+    pub fn directories(&self) -> &BTreeSet<String> {
+        &self.directories
+    }
+
+    /// Compare a site listing to this manifest. `added` holds paths that only exist locally,
+    /// `removed` holds paths that only exist in `remote` and `modified` holds files whose contents differ
+    pub fn diff(&self, remote: &[ListEntry]) -> ListingDiff {
+        diff_listings(&index(remote), &self.index())
+    }
+
+    fn index(&self) -> Index {
+        self.files
+            .iter()
+            .map(|(path, file)| (path.clone(), Some(file.sha1_hash.clone())))
+            .chain(self.directories.iter().map(|path| (path.clone(), None)))
+            .collect()
+    }
+}
+
+/// A record of every file in a local directory, from [`build_manifest`], that can be stored
+/// as JSON to pin a known good deploy and compared against the site later.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The path, hex encoded SHA-1 hash and size in bytes of each file, sorted by path.
+    /// Paths are relative to the directory and use `/` as the separator
+    pub entries: Vec<(String, String, u64)>,
+}
+
+impl Manifest {
+    /// Compare a site listing to this manifest. `added` holds files only in the manifest,
+    /// `removed` holds files only in `remote` and `modified` holds files whose contents differ.
+    /// Directories in `remote` are ignored since the manifest doesn't record them
+    pub fn diff(&self, remote: &[ListEntry]) -> ListingDiff {
+        let mut remote = index(remote);
+        remote.retain(|_, hash| hash.is_some());
+
+        let local = self
+            .entries
+            .iter()
+            .map(|(path, hash, _)| (path.clone(), Some(hash.to_ascii_lowercase())))
+            .collect();
+
+        diff_listings(&remote, &local)
+    }
+}
+
+/// Hash every file under the local directory `root` into a [`Manifest`]. This makes no requests
+pub fn build_manifest<P: AsRef<Path>>(root: P) -> Result<Manifest, NeocitiesError> {
+    let entries = LocalManifest::build(root)?
+        .files
+        .into_iter()
+        .map(|(path, file)| (path, file.sha1_hash, file.size))
+        .collect();
+
+    Ok(Manifest { entries })
+}
+
+/// Find files and directories under the local directory `root` whose paths differ only by case,
+/// like `Index.html` and `index.html`, which can overwrite each other when moved between
+/// case sensitive and case insensitive filesystems. Returns each group of colliding paths,
+/// or nothing if there are no collisions. This makes no requests
+pub fn check_case_collisions<P: AsRef<Path>>(root: P) -> Result<Vec<Vec<PathBuf>>, NeocitiesError> {
+    let root = root.as_ref();
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    for entry in WalkDir::new(root).min_depth(1) {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let path = remote_path(root, entry.path()).to_lowercase();
+
+        groups.entry(path).or_default().push(entry.into_path());
+    }
+
+    Ok(groups
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect())
+}
+
+// Rough time spent on each upload request besides sending the file, such as connection setup
+const REQUEST_OVERHEAD: Duration = Duration::from_millis(200);
+
+// Files sent per request when uploading many files at once
+pub(crate) const UPLOAD_BATCH_SIZE: usize = 50;
+
+// Downloads running at once when copying between sites
+const COPY_CONCURRENCY: usize = 4;
+
+// Maps each path to the SHA-1 hash of the file, or `None` for directories
+pub(crate) type Index = BTreeMap<String, Option<String>>;
+
+pub(crate) fn index(entries: &[ListEntry]) -> Index {
+    entries
+        .iter()
+        .map(|entry| match entry {
+            ListEntry::File {
+                path, sha1_hash, ..
+            } => (path.clone(), Some(sha1_hash.to_ascii_lowercase())),
+            ListEntry::Directory { path, .. } => (path.clone(), None),
+        })
+        .collect()
+}
+
+// The path a local file under `root` would have on a site
+pub(crate) fn remote_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+// The local path of the remote `path` under `root`, or `None` if `path` could lead outside of
+// `root`, such as an absolute path or one containing `..`
+pub(crate) fn local_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path);
+    let safe = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    (safe && !path.is_empty()).then(|| root.join(relative))
+}
+
+// Join a normalized remote directory and a path under it
+pub(crate) fn join_remote(dir: &str, path: &str) -> String {
+    if dir.is_empty() {
+        path.to_string()
+    } else if path.is_empty() {
+        dir.to_string()
+    } else {
+        format!("{}/{}", dir, path)
+    }
+}
+
+// The paths to delete to remove everything in `removed`, except for `index.html`.
+// Deleting a directory deletes everything in it, so anything under another removed path is skipped
+fn stale_paths(removed: &[String]) -> Vec<String> {
+    let dirs: BTreeSet<&String> = removed.iter().collect();
+
+    removed
+        .iter()
+        .filter(|path| *path != "index.html")
+        .filter(|path| {
+            !dirs
+                .iter()
+                .any(|dir| path.starts_with(&format!("{}/", dir)))
+        })
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn diff_listings(from: &Index, to: &Index) -> ListingDiff {
+    let mut diff = ListingDiff::default();
+
+    for (path, hash) in to {
+        match from.get(path) {
+            None => diff.added.push(path.clone()),
+            Some(old) if old != hash => diff.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = from
+        .keys()
+        .filter(|path| !to.contains_key(*path))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+impl Neocities {
+    /// Deploy a site held in memory, such as the output of a static site generator.
+    /// Each file is a pair of its path on the site and its contents.
+    ///
+    /// Files are uploaded in batches with [`Neocities::upload_many`]. If `prune` is set, files on
+    /// the site that are not in `files` are deleted afterwards, except for `index.html` which
+    /// can't be deleted. Directories are never pruned.
+    ///
+    /// If two files have the same path, [`NeocitiesError::InvalidInput`] is returned naming it
+    /// before any batch is uploaded
+    pub async fn deploy_files(
+        &self,
+        files: Vec<(String, Vec<u8>)>,
+        prune: bool,
+    ) -> Result<DeployReport, NeocitiesError> {
+        check_duplicates(files.iter().map(|(path, _)| path.as_str()))?;
+        let mut report = DeployReport::default();
+        let mut files = files.into_iter().peekable();
+
+        while files.peek().is_some() {
+            let batch: Vec<_> = files
+                .by_ref()
+                .take(UPLOAD_BATCH_SIZE)
+                .map(|(path, file)| (path.trim_matches('/').to_string(), file))
+                .collect();
+            let paths: Vec<String> = batch.iter().map(|(path, _)| path.clone()).collect();
+
+            self.upload_many(batch).await?;
+            report.uploaded.extend(paths);
+        }
+
+        if prune {
+            let keep: HashSet<&str> = report.uploaded.iter().map(String::as_str).collect();
+            report.deleted = self.prune(&keep).await?;
+        }
+
+        Ok(report)
+    }
+
+    // Delete every file on the site not in `keep`, except for `index.html`
+    pub(crate) async fn prune(&self, keep: &HashSet<&str>) -> Result<Vec<String>, NeocitiesError> {
+        let stale: Vec<String> = self
+            .list("")
+            .await?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                ListEntry::File { path, .. }
+                    if path != "index.html" && !keep.contains(path.as_str()) =>
+                {
+                    Some(path)
+                }
+                _ => None,
+            })
+            .collect();
+
+        if !stale.is_empty() {
+            self.delete(&stale).await?;
+        }
+
+        Ok(stale)
+    }
+
+    /// Estimate how long uploading `files` will take, for showing an ETA before a deploy.
+    /// Each file is a pair of its path and size in bytes, and `bytes_per_sec` is the expected
+    /// upload bandwidth. A fixed overhead is added per file, so many small files take longer
+    /// than one large file of the same total size. This makes no requests
+    pub fn estimate_deploy(&self, files: &[(String, u64)], bytes_per_sec: u64) -> Duration {
+        let total: u64 = files.iter().map(|(_, size)| size).sum();
+        let transfer = Duration::from_secs_f64(total as f64 / bytes_per_sec.max(1) as f64);
+
+        transfer + REQUEST_OVERHEAD * files.len() as u32
+    }
+
+    /// Download every file on the current [`Neocities`] site updated after `since` into the
+    /// local directory `root`, creating directories as needed. Returns the paths of the
+    /// downloaded files. Files whose timestamp can't be parsed are always downloaded.
+    /// If the server lists a path that would be written outside of `root`, such as one containing
+    /// `..`, [`NeocitiesError::InvalidResponse`] is returned before it is downloaded.
+    ///
+    /// This only looks at timestamps, so files deleted from the site are not removed locally.
+    /// Run [`Neocities::drift`] on `root` occasionally for a full comparison, where files
+    /// deleted from the site show up as `added`
+    pub async fn mirror_incremental<P: AsRef<Path>>(
+        &self,
+        root: P,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<String>, NeocitiesError> {
+        let root = root.as_ref();
+        let mut updated = Vec::new();
+
+        for entry in self.list("").await? {
+            if let ListEntry::Directory { .. } = entry {
+                continue;
+            }
+
+            if entry.updated_at_utc().is_none_or(|time| time > since) {
+                let path = entry.path();
+                let local = validate_path(path)
+                    .ok()
+                    .and_then(|path| local_path(root, path))
+                    .ok_or_else(|| {
+                        NeocitiesError::InvalidResponse(format!(
+                            "server listed the unsafe path `{}`",
+                            path
+                        ))
+                    })?;
+
+                self.download_to(path, local).await?;
+                updated.push(path.to_string());
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Compare the sites of two clients, such as when migrating between accounts.
+    /// `added` holds paths only on `b`, `removed` holds paths only on `a` and `modified`
+    /// holds files whose SHA-1 hashes differ. Both sites are listed at the same time
+    pub async fn compare_sites(
+        a: &Neocities,
+        b: &Neocities,
+    ) -> Result<ListingDiff, NeocitiesError> {
+        let (a, b) = try_join(a.list(""), b.list("")).await?;
+
+        Ok(ListEntry::diff(&a, &b))
+    }
+
+    /// Copy the site of `from` to the site of `to`, such as when migrating between accounts.
+    ///
+    /// Only files that are missing or different on `to` are downloaded and uploaded,
+    /// with a few downloads running at once. If `prune` is set, files and directories on `to`
+    /// that are not on `from` are deleted afterwards, except for `index.html` which can't be deleted
+    pub async fn copy_site(
+        from: &Neocities,
+        to: &Neocities,
+        prune: bool,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let (source, dest) = try_join(from.list(""), to.list("")).await?;
+        let source_index = index(&source);
+        let diff = diff_listings(&index(&dest), &source_index);
+        let mut report = DeployReport::default();
+
+        let changed: Vec<String> = diff
+            .added
+            .iter()
+            .chain(&diff.modified)
+            .filter(|path| source_index.get(*path).is_some_and(Option::is_some))
+            .cloned()
+            .collect();
+
+        for batch in changed.chunks(UPLOAD_BATCH_SIZE) {
+            let mut files = Vec::new();
+            for (path, file) in from.download_many(batch, COPY_CONCURRENCY).await {
+                files.push((path, file?));
+            }
+
+            to.upload_many(files).await?;
+            report.uploaded.extend_from_slice(batch);
+        }
+
+        if prune {
+            let stale = stale_paths(&diff.removed);
+
+            if !stale.is_empty() {
+                to.delete(&stale).await?;
+                report.deleted = stale;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Compare the local directory `root` to the current [`Neocities`] site without changing anything.
+    ///
+    /// `added` holds paths that only exist locally, `removed` holds paths that only exist
+    /// on the site and `modified` holds files whose contents differ.
+    /// This is the same set of changes a deploy of `root` would need to make
+    pub async fn drift<P: AsRef<Path>>(&self, root: P) -> Result<ListingDiff, NeocitiesError> {
+        self.plan(&LocalManifest::build(root)?).await
+    }
+
+    /// Check every file under the local directory `local_root` against the current [`Neocities`] site,
+    /// returning the files that are missing on either side or whose contents differ.
+    ///
+    /// Local files are hashed and compared to the SHA-1 hashes in the site listing,
+    /// so nothing is downloaded. Directories are ignored
+    pub async fn verify_tree<P: AsRef<Path>>(
+        &self,
+        local_root: P,
+    ) -> Result<Vec<PathMismatch>, NeocitiesError> {
+        let manifest = LocalManifest::build(local_root)?;
+        let mut remote: Index = index(&self.list("").await?)
+            .into_iter()
+            .filter(|(_, hash)| hash.is_some())
+            .collect();
+        let mut mismatches = Vec::new();
+
+        for (path, file) in &manifest.files {
+            let remote_sha1 = remote.remove(path).flatten();
+
+            if remote_sha1.as_deref() != Some(file.sha1_hash.as_str()) {
+                mismatches.push(PathMismatch {
+                    path: path.clone(),
+                    local_sha1: Some(file.sha1_hash.clone()),
+                    remote_sha1,
+                });
+            }
+        }
+
+        mismatches.extend(remote.into_iter().map(|(path, remote_sha1)| PathMismatch {
+            path,
+            local_sha1: None,
+            remote_sha1,
+        }));
+        mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(mismatches)
+    }
+
+    /// Check that the current [`Neocities`] site exactly matches the local directory `root`,
+    /// such as after a deploy in CI, without changing anything. See [`Neocities::verify_tree`]
+    pub async fn verify_against<P: AsRef<Path>>(
+        &self,
+        root: P,
+    ) -> Result<VerifyReport, NeocitiesError> {
+        let mut report = VerifyReport::default();
+
+        for mismatch in self.verify_tree(root).await? {
+            match (mismatch.local_sha1, mismatch.remote_sha1) {
+                (Some(_), Some(_)) => report.mismatched.push(mismatch.path),
+                (Some(_), None) => report.missing_remote.push(mismatch.path),
+                (None, _) => report.extra_remote.push(mismatch.path),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Deploy the local directory `new_root` to the current [`Neocities`] site, assuming the site
+    /// currently matches `old_root`, such as the previous build kept around by CI.
+    ///
+    /// The two directories are compared locally and only the differences are uploaded or
+    /// deleted with [`Neocities::apply`], so the site is never listed. If the site has drifted
+    /// from `old_root` those changes are not noticed, use [`Neocities::drift`] to check
+    pub async fn sync_delta<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        old_root: P,
+        new_root: Q,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let old = LocalManifest::build(old_root)?;
+        let new = LocalManifest::build(new_root)?;
+
+        self.apply(&new, &diff_listings(&old.index(), &new.index()))
+            .await
+    }
+
+    /// Make the current [`Neocities`] site match `manifest`, such as one approved before the
+    /// deploy, uploading the files in it from the local directory `root`.
+    ///
+    /// Files whose hash on the site differs from the manifest are uploaded. Before anything is
+    /// uploaded, each of those files is hashed again and [`NeocitiesError::InvalidInput`] is
+    /// returned if it no longer matches the manifest, so only what was approved gets deployed.
+    /// Files on the site that are not in the manifest are left alone.
+    ///
+    /// Every path in the manifest must be relative to `root` without `.` or `..` segments,
+    /// otherwise [`NeocitiesError::InvalidInput`] is returned before anything is read or sent
+    pub async fn apply_manifest<P: AsRef<Path>>(
+        &self,
+        root: P,
+        manifest: &Manifest,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let root = root.as_ref();
+
+        // A manifest can come from anywhere, so check that every path stays inside `root`
+        let mut expected: HashMap<&str, (&str, PathBuf)> = HashMap::new();
+        for (path, hash, _) in &manifest.entries {
+            let local = local_path(root, path).ok_or_else(|| {
+                NeocitiesError::InvalidInput(format!(
+                    "manifest path `{}` is not a relative path inside the directory",
+                    path
+                ))
+            })?;
+            expected.insert(path.as_str(), (hash.as_str(), local));
+        }
+
+        let mut diff = manifest.diff(&self.list("").await?);
+        diff.removed.clear();
+        let mut files = BTreeMap::new();
+
+        for path in diff.added.iter().chain(&diff.modified) {
+            // Added and modified paths all come from the manifest
+            let (hash, local) = &expected[path.as_str()];
+            let sha1_hash = sha1_file(local)?;
+
+            if !hash.eq_ignore_ascii_case(&sha1_hash) {
+                return Err(NeocitiesError::InvalidInput(format!(
+                    "`{}` no longer matches the manifest",
+                    path
+                )));
+            }
+
+            let size = std::fs::metadata(local)?.len();
+            files.insert(path.clone(), LocalFile { sha1_hash, size });
+        }
+
+        let local = LocalManifest {
+            root: root.to_path_buf(),
+            files,
+            directories: BTreeSet::new(),
+        };
+
+        self.apply(&local, &diff).await
+    }
+
+    /// Upload every file under the local directory `root` that isn't already on the current
+    /// [`Neocities`] site, skipping files recorded in `state` with the same hash as a previous run.
+    ///
+    /// The site is listed once and compared to a [`LocalManifest`] of `root`. Files that differ
+    /// are uploaded in batches with [`Neocities::upload_many`], and `state` is updated after each
+    /// batch, so if the deploy is interrupted it can be saved and passed back in to continue
+    /// from where it stopped. Nothing is deleted from the site
+    pub async fn resume_deploy<P: AsRef<Path>>(
+        &self,
+        root: P,
+        state: &mut DeployState,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let manifest = LocalManifest::build(root)?;
+        let diff = self.plan(&manifest).await?;
+        let changed: BTreeSet<&String> = diff.added.iter().chain(&diff.modified).collect();
+        let mut report = DeployReport::default();
+        let mut pending = Vec::new();
+
+        for (path, file) in &manifest.files {
+            if state.uploaded.get(path) == Some(&file.sha1_hash) {
+                continue;
+            }
+
+            if changed.contains(path) {
+                pending.push((path, file));
+            } else {
+                state.uploaded.insert(path.clone(), file.sha1_hash.clone());
+            }
+        }
+
+        for batch in pending.chunks(UPLOAD_BATCH_SIZE) {
+            let mut files = Vec::new();
+            for (path, _) in batch {
+                files.push(((*path).clone(), std::fs::read(manifest.root.join(path))?));
+            }
+
+            self.upload_many(files).await?;
+
+            for (path, file) in batch {
+                report.uploaded.push((*path).clone());
+                state
+                    .uploaded
+                    .insert((*path).clone(), file.sha1_hash.clone());
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Compare a [`LocalManifest`] to the current [`Neocities`] site without changing anything,
+    /// returning the changes [`Neocities::apply`] would need to make. See [`Neocities::drift`]
+    pub async fn plan(&self, manifest: &LocalManifest) -> Result<ListingDiff, NeocitiesError> {
+        Ok(manifest.diff(&self.list("").await?))
+    }
+
+    /// Make the changes in `diff`, usually from [`Neocities::plan`], to the current [`Neocities`] site
+    /// using the files in `manifest`.
+    ///
+    /// Added and modified files are uploaded in batches with [`Neocities::upload_many`], then
+    /// removed paths are deleted, except for `index.html` which can't be deleted.
+    /// Added directories are created implicitly by the files uploaded into them
+    pub async fn apply(
+        &self,
+        manifest: &LocalManifest,
+        diff: &ListingDiff,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let mut report = DeployReport::default();
+
+        let changed: Vec<&String> = diff
+            .added
+            .iter()
+            .chain(&diff.modified)
+            .filter(|path| manifest.files.contains_key(*path))
+            .collect();
+
+        for batch in changed.chunks(UPLOAD_BATCH_SIZE) {
+            let mut files = Vec::new();
+            for path in batch {
+                files.push(((*path).clone(), std::fs::read(manifest.root.join(path))?));
+            }
+
+            self.upload_many(files).await?;
+            report
+                .uploaded
+                .extend(batch.iter().map(|path| (*path).clone()));
+        }
+
+        let stale = stale_paths(&diff.removed);
+
+        if !stale.is_empty() {
+            self.delete(&stale).await?;
+            report.deleted = stale;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, sha1_hash: &str) -> ListEntry {
+        ListEntry::File {
+            path: path.to_string(),
+            size: 0,
+            updated_at: String::new(),
+            sha1_hash: sha1_hash.to_string(),
+        }
+    }
+
+    fn dir(path: &str) -> ListEntry {
+        ListEntry::Directory {
+            path: path.to_string(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn diff_finds_added_removed_and_modified_paths() {
+        let old = [
+            file("index.html", "aaa"),
+            file("about.html", "bbb"),
+            dir("old"),
+            file("old/page.html", "ccc"),
+            dir("images"),
+        ];
+        let new = [
+            file("index.html", "AAA"),
+            file("about.html", "ddd"),
+            dir("images"),
+            file("images/cat.png", "eee"),
+        ];
+
+        let diff = ListEntry::diff(&old, &new);
+
+        assert_eq!(diff.added, ["images/cat.png"]);
+        assert_eq!(diff.removed, ["old", "old/page.html"]);
+        // Hashes are compared ignoring case
+        assert_eq!(diff.modified, ["about.html"]);
+    }
+
+    #[test]
+    fn diff_of_identical_listings_is_empty() {
+        let listing = [file("index.html", "aaa"), dir("images")];
+
+        assert!(ListEntry::diff(&listing, &listing).is_empty());
+    }
+
+    #[test]
+    fn stale_paths_skip_index_html_and_paths_inside_removed_directories() {
+        let removed = [
+            "index.html",
+            "old",
+            "old/page.html",
+            "old/images/cat.png",
+            "older/page.html",
+        ]
+        .map(String::from);
+
+        assert_eq!(stale_paths(&removed), ["old", "older/page.html"]);
+    }
+
+    #[test]
+    fn by_category_uses_the_longest_matching_prefix() {
+        let report = DeployReport {
+            uploaded: ["images/cat.png", "images/icons/home.svg", "index.html"]
+                .map(String::from)
+                .to_vec(),
+            deleted: ["images/old.png".to_string()].to_vec(),
+        };
+        let categories = HashMap::from([
+            ("images/".to_string(), "assets".to_string()),
+            ("/images/icons/".to_string(), "icons".to_string()),
+        ]);
+
+        let reports = report.by_category(&categories);
+
+        assert_eq!(
+            reports.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["", "assets", "icons"]
+        );
+        assert_eq!(reports[""].uploaded, ["index.html"]);
+        assert_eq!(reports["assets"].uploaded, ["images/cat.png"]);
+        assert_eq!(reports["assets"].deleted, ["images/old.png"]);
+        assert_eq!(reports["icons"].uploaded, ["images/icons/home.svg"]);
+        assert!(reports["icons"].deleted.is_empty());
+    }
+
+    #[test]
+    fn manifest_diff_ignores_remote_directories_and_hash_case() {
+        let manifest = Manifest {
+            entries: vec![
+                ("about.html".to_string(), "BBB".to_string(), 5),
+                ("images/cat.png".to_string(), "ccc".to_string(), 4),
+                ("index.html".to_string(), "aaa".to_string(), 4),
+            ],
+        };
+        let remote = [
+            file("index.html", "aaa"),
+            file("about.html", "bbb"),
+            dir("images"),
+            dir("old"),
+            file("old/page.html", "ddd"),
+        ];
+
+        let diff = manifest.diff(&remote);
+
+        assert_eq!(diff.added, ["images/cat.png"]);
+        assert_eq!(diff.removed, ["old/page.html"]);
+        assert!(diff.modified.is_empty());
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glob::Pattern;
+use reqwest::multipart::Form;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+    check_duplicates, extension_allowed,
+    sync::{join_remote, remote_path},
+    CancellationToken, Info, Neocities, NeocitiesError,
+};
+
+/// Size in bytes above which text files are compressed by [`UploadDirOptions::compress_text`]
+#[cfg(feature = "gzip")]
+pub const COMPRESS_THRESHOLD: u64 = 16 * 1024;
+
+// Extensions of files worth compressing
+#[cfg(feature = "gzip")]
+const TEXT_EXTENSIONS: &[&str] = &["css", "csv", "js", "json", "md", "mjs", "svg", "txt", "xml"];
+
+type IndexTemplate = Arc<dyn Fn(&str, &[String]) -> String + Send + Sync>;
+type Priority = Arc<dyn Fn(&str) -> u32 + Send + Sync>;
+
+/// How [`Neocities::upload_dir`] and [`LocalManifest::build_with_symlinks`] treat symbolic links
+/// in the directory being read.
+///
+/// [`LocalManifest::build_with_symlinks`]: crate::LocalManifest::build_with_symlinks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Use the files and directories links point to as if they were inside the directory,
+    /// even if they are somewhere else entirely. Links that loop back on themselves
+    /// cause an error
+    Follow,
+    /// Leave out links and anything they point to
+    #[default]
+    Skip,
+    /// Return [`NeocitiesError::InvalidInput`] on the first link found, before it is uploaded
+    Error,
+}
+
+impl SymlinkPolicy {
+    // Walk the entries of `walker`, following, leaving out or failing on links below its root
+    pub(crate) fn walk(
+        self,
+        walker: WalkDir,
+    ) -> impl Iterator<Item = Result<DirEntry, NeocitiesError>> {
+        walker
+            .follow_links(self == SymlinkPolicy::Follow)
+            .into_iter()
+            .filter_map(move |entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => return Some(Err(std::io::Error::from(e).into())),
+                };
+
+                if entry.depth() == 0 || !entry.path_is_symlink() {
+                    return Some(Ok(entry));
+                }
+
+                match self {
+                    SymlinkPolicy::Follow => Some(Ok(entry)),
+                    SymlinkPolicy::Skip => None,
+                    SymlinkPolicy::Error => Some(Err(NeocitiesError::InvalidInput(format!(
+                        "`{}` is a symbolic link",
+                        entry.path().display()
+                    )))),
+                }
+            })
+    }
+}
+
+/// The result of [`Neocities::upload_dir`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UploadDirReport {
+    /// Remote paths of the uploaded files, including any generated `index.html` pages
+    pub uploaded: Vec<String>,
+    /// Whether uploading was stopped early through [`UploadDirOptions::cancel_on`],
+    /// in which case `uploaded` only holds the files sent before that
+    pub cancelled: bool,
+}
+
+/// Options for [`Neocities::upload_dir`].
+#[derive(Clone, Default)]
+pub struct UploadDirOptions {
+    remote_prefix: Option<String>,
+    mime_types: Vec<(String, String)>,
+    index_template: Option<IndexTemplate>,
+    priority: Option<Priority>,
+    cancel: Option<CancellationToken>,
+    symlinks: SymlinkPolicy,
+    #[cfg(feature = "sniff")]
+    sniff: bool,
+    #[cfg(feature = "gzip")]
+    compress_above: Option<u64>,
+}
+
+impl fmt::Debug for UploadDirOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("UploadDirOptions");
+        f.field("remote_prefix", &self.remote_prefix)
+            .field("mime_types", &self.mime_types)
+            .field("generate_index", &self.index_template.is_some())
+            .field("ordered", &self.priority.is_some())
+            .field("cancel", &self.cancel)
+            .field("symlinks", &self.symlinks);
+
+        #[cfg(feature = "sniff")]
+        f.field("sniff", &self.sniff);
+        #[cfg(feature = "gzip")]
+        f.field("compress_above", &self.compress_above);
+
+        f.finish()
+    }
+}
+
+impl UploadDirOptions {
+    /// Create the default options, uploading every file as is to the root of the site
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Upload files under the directory `prefix` of the site, so with a prefix of `blog` the
+    /// local file `post.html` is uploaded to `blog/post.html`.
+    /// Leading and trailing slashes on the prefix are ignored
+    pub fn remote_prefix<T: Into<String>>(mut self, prefix: T) -> Self {
+        self.remote_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Set the MIME types of specific files instead of leaving them to the server, such as for
+    /// extensionless files like `CNAME`. Keys are remote paths including any prefix, or glob
+    /// patterns like `*.webmanifest`. An exact path takes priority over patterns, and if several
+    /// patterns match a file the first one given is used. Paths are matched before any `.gz`
+    /// is added by [`UploadDirOptions::compress_text`]
+    pub fn mime_types<I, K, V>(mut self, mime_types: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.mime_types = mime_types
+            .into_iter()
+            .map(|(path, mime)| (path.into(), mime.into()))
+            .collect();
+        self
+    }
+
+    /// Upload a simple page linking to everything in a directory as its `index.html`,
+    /// for every directory that doesn't have an `index.html` of its own
+    pub fn generate_index(self) -> Self {
+        self.index_template(default_index)
+    }
+
+    /// Like [`UploadDirOptions::generate_index`], but build each page with `template`.
+    /// It is called with the remote path of the directory, empty for the root of the site,
+    /// and the names of its entries. Subdirectories end with a `/`
+    pub fn index_template<F>(mut self, template: F) -> Self
+    where
+        F: Fn(&str, &[String]) -> String + Send + Sync + 'static,
+    {
+        self.index_template = Some(Arc::new(template));
+        self
+    }
+
+    /// Detect the MIME type of files whose remote path has no extension from their first bytes,
+    /// such as a PNG image uploaded as `avatar`. Types set with [`UploadDirOptions::mime_types`]
+    /// take priority, and files of an unrecognized type are left to the server
+    #[cfg(feature = "sniff")]
+    pub fn sniff_mime_types(mut self) -> Self {
+        self.sniff = true;
+        self
+    }
+
+    /// Gzip text files such as CSS, JavaScript and JSON larger than
+    /// [`COMPRESS_THRESHOLD`] bytes, uploading them with a `.gz` suffix instead, so
+    /// `data.json` is uploaded as `data.json.gz`. HTML pages and other files are always uploaded as is.
+    ///
+    /// Neocities serves files exactly as they are stored and never sets `Content-Encoding`,
+    /// so browsers won't decompress these files on their own. Only use this for files fetched
+    /// by your own scripts, which can decompress them with `DecompressionStream("gzip")`, and
+    /// update any links to point to the `.gz` path. `.gz` files may also need a supporter account
+    #[cfg(feature = "gzip")]
+    pub fn compress_text(self, enabled: bool) -> Self {
+        self.compress_threshold(enabled.then_some(COMPRESS_THRESHOLD))
+    }
+
+    /// Like [`UploadDirOptions::compress_text`], but only compress text files larger than
+    /// `threshold` bytes, or disable compression with `None`
+    #[cfg(feature = "gzip")]
+    pub fn compress_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.compress_above = threshold;
+        self
+    }
+
+    /// Upload HTML pages after every other file, and `index.html` pages after other pages,
+    /// so stylesheets, scripts and images are already in place when the pages that use them
+    /// go live. By default files are uploaded in the order they are found in the directory
+    pub fn pages_last(self) -> Self {
+        self.upload_priority(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+
+            if name == "index.html" {
+                2
+            } else if name.ends_with(".html") || name.ends_with(".htm") {
+                1
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Upload files in ascending order of the priority `priority` gives their remote path,
+    /// keeping the order they are found in for files of equal priority.
+    /// Generated `index.html` pages are always uploaded last
+    pub fn upload_priority<F>(mut self, priority: F) -> Self
+    where
+        F: Fn(&str) -> u32 + Send + Sync + 'static,
+    {
+        self.priority = Some(Arc::new(priority));
+        self
+    }
+
+    /// Set how symbolic links are treated, see [`SymlinkPolicy`].
+    /// By default they are skipped, so nothing outside the directory is uploaded by accident
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Stop uploading once `cancel` is cancelled, such as from a stop button in a GUI.
+    /// The file being uploaded at the time is allowed to finish, then [`Neocities::upload_dir`]
+    /// returns without uploading the rest or any generated pages, with
+    /// [`UploadDirReport::cancelled`] set and the files that were uploaded before stopping
+    pub fn cancel_on(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn mime_type(&self, path: &str) -> Option<&str> {
+        self.mime_types
+            .iter()
+            .find(|(exact, _)| exact == path)
+            .or_else(|| {
+                self.mime_types
+                    .iter()
+                    .find(|(pattern, _)| Pattern::new(pattern).is_ok_and(|p| p.matches(path)))
+            })
+            .map(|(_, mime)| mime.as_str())
+    }
+
+    // Whether a file at `path` of `size` bytes is a large enough text file to gzip
+    #[cfg(feature = "gzip")]
+    fn should_compress(&self, path: &str, size: u64) -> bool {
+        let compressible = path
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| TEXT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+
+        self.compress_above
+            .is_some_and(|threshold| compressible && size > threshold)
+    }
+
+    // Gzip `file` if it's a large enough text file, returning the new path and contents
+    #[cfg(feature = "gzip")]
+    fn compress(&self, path: String, file: Vec<u8>) -> std::io::Result<(String, Vec<u8>)> {
+        use std::io::Write;
+
+        if !self.should_compress(&path, file.len() as u64) {
+            return Ok((path, file));
+        }
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&file)?;
+
+        Ok((format!("{}.gz", path), encoder.finish()?))
+    }
+
+    // The remote path the local file `local_path` at `relative` is uploaded to,
+    // including the `.gz` added by compression
+    #[cfg_attr(not(feature = "gzip"), allow(unused_variables))]
+    fn upload_path(
+        &self,
+        prefix: &str,
+        relative: &str,
+        local_path: &Path,
+    ) -> std::io::Result<String> {
+        let path = join_remote(prefix, relative);
+
+        #[cfg(feature = "gzip")]
+        if self.should_compress(&path, std::fs::metadata(local_path)?.len()) {
+            return Ok(format!("{}.gz", path));
+        }
+
+        Ok(path)
+    }
+
+    #[cfg(feature = "sniff")]
+    fn sniff_mime_type(&self, path: &str, file: &[u8]) -> Option<&'static str> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if !self.sniff || name.contains('.') {
+            return None;
+        }
+
+        infer::get(file).map(|kind| kind.mime_type())
+    }
+}
+
+impl Neocities {
+    /// Find the files under the local directory `root` that an account isn't allowed to upload
+    /// because of their extension, see [`Info::allowed_extensions`]. Set `supporter` for
+    /// supporter accounts, which can upload anything.
+    /// This only looks at local files, so it can be run before [`Neocities::upload_dir`]
+    /// to warn about files the server would reject. Symbolic links are not followed
+    pub fn disallowed_files<P: AsRef<Path>>(
+        &self,
+        root: P,
+        supporter: bool,
+    ) -> Result<Vec<PathBuf>, NeocitiesError> {
+        let allowed = match Info::allowed_extensions(supporter) {
+            Some(allowed) => allowed,
+            None => return Ok(Vec::new()),
+        };
+        let mut disallowed = Vec::new();
+
+        for entry in WalkDir::new(root).min_depth(1) {
+            let entry = entry.map_err(std::io::Error::from)?;
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            if !extension_allowed(allowed, entry.path()) {
+                disallowed.push(entry.into_path());
+            }
+        }
+
+        Ok(disallowed)
+    }
+
+    /// Upload every file under the local directory `root` to the current [`Neocities`] site,
+    /// with `root` treated as the root of the site. See [`UploadDirOptions`] for the available options.
+    /// Returns an [`UploadDirReport`] with the remote paths of the uploaded files.
+    ///
+    /// If two files end up with the same remote path, such as `data.json` compressed by
+    /// [`UploadDirOptions::compress_text`] next to an existing `data.json.gz`,
+    /// [`NeocitiesError::InvalidInput`] is returned naming the path before anything is uploaded
+    pub async fn upload_dir<P: AsRef<Path>>(
+        &self,
+        root: P,
+        options: &UploadDirOptions,
+    ) -> Result<UploadDirReport, NeocitiesError> {
+        let root = root.as_ref();
+        let prefix = options
+            .remote_prefix
+            .as_deref()
+            .map_or("", |prefix| prefix.trim_matches('/'));
+        let mut uploaded = Vec::new();
+        // Paths relative to `root` and local paths of every file to upload
+        let mut files = Vec::new();
+        // Names of the entries in each directory, relative to `root`
+        let mut directories: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for entry in options.symlinks.walk(WalkDir::new(root)) {
+            let entry = entry?;
+            let relative = remote_path(root, entry.path());
+
+            if entry.file_type().is_dir() {
+                if entry.depth() > 0 {
+                    let (parent, name) = relative.rsplit_once('/').unwrap_or(("", &relative));
+                    let name = format!("{}/", name);
+                    directories
+                        .entry(parent.to_string())
+                        .or_default()
+                        .push(name);
+                }
+                directories.entry(relative).or_default();
+            } else {
+                files.push((relative, entry.into_path()));
+            }
+        }
+
+        if let Some(priority) = &options.priority {
+            files.sort_by_key(|(relative, _)| priority(&join_remote(prefix, relative)));
+        }
+
+        // Work out every remote path first, so a collision is found before anything is uploaded
+        let paths = files
+            .iter()
+            .map(|(relative, local_path)| options.upload_path(prefix, relative, local_path))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        check_duplicates(paths.iter().map(String::as_str))?;
+
+        for (relative, local_path) in files {
+            if options.is_cancelled() {
+                return Ok(UploadDirReport {
+                    uploaded,
+                    cancelled: true,
+                });
+            }
+
+            let path = join_remote(prefix, &relative);
+            let file = std::fs::read(local_path)?;
+            let mime = options.mime_type(&path);
+            #[cfg(feature = "gzip")]
+            let (path, file) = options.compress(path, file)?;
+
+            #[cfg(feature = "sniff")]
+            let mime = mime.or_else(|| options.sniff_mime_type(&path, &file));
+            let mut part = self.file_part(file).file_name(path.clone());
+
+            if let Some(mime) = mime {
+                part = part.mime_str(mime)?;
+            }
+
+            self.upload_form(Form::new().part(path.clone(), part))
+                .await?;
+
+            let parent = relative.rsplit_once('/').map_or("", |(parent, _)| parent);
+            let name = path.rsplit('/').next().unwrap_or(&path);
+            directories
+                .entry(parent.to_string())
+                .or_default()
+                .push(name.to_string());
+
+            uploaded.push(path);
+        }
+
+        if let Some(template) = &options.index_template {
+            for (dir, entries) in &directories {
+                if entries.iter().any(|name| name == "index.html") {
+                    continue;
+                }
+
+                if options.is_cancelled() {
+                    return Ok(UploadDirReport {
+                        uploaded,
+                        cancelled: true,
+                    });
+                }
+
+                let dir = join_remote(prefix, dir);
+                let path = join_remote(&dir, "index.html");
+
+                self.upload(path.clone(), template(&dir, entries)).await?;
+                uploaded.push(path);
+            }
+        }
+
+        Ok(UploadDirReport {
+            uploaded,
+            cancelled: false,
+        })
+    }
+}
+
+fn default_index(dir: &str, entries: &[String]) -> String {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let title = escape(&format!("Index of /{}", dir));
+
+    let links: String = entries
+        .iter()
+        .map(|name| format!("      <li><a href=\"{0}\">{0}</a></li>\n", escape(name)))
+        .collect();
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=\"utf-8\">
+    <title>{0}</title>
+  </head>
+  <body>
+    <h1>{0}</h1>
+    <ul>
+{1}    </ul>
+  </body>
+</html>
+",
+        title, links
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_index_links_every_entry() {
+        let page = default_index("blog", &["drafts/".to_string(), "post.html".to_string()]);
+
+        assert!(page.contains("<title>Index of /blog</title>"));
+        assert!(page.contains("<li><a href=\"drafts/\">drafts/</a></li>"));
+        assert!(page.contains("<li><a href=\"post.html\">post.html</a></li>"));
+    }
+
+    #[test]
+    fn default_index_escapes_names() {
+        let page = default_index("", &["<b>&\"</b>.html".to_string()]);
+
+        assert!(page.contains("<title>Index of /</title>"));
+        assert!(page.contains("&lt;b&gt;&amp;&quot;&lt;/b&gt;.html"));
+        assert!(!page.contains("<b>"));
+    }
+}
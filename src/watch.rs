@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+use tokio::{sync::mpsc, time::timeout};
+
+use crate::{sync::remote_path, DeployReport, Neocities, NeocitiesError, UploadOutcome};
+
+impl Neocities {
+    /// Watch the local directory `root` and upload files to the current [`Neocities`] site
+    /// as they change, with `root` treated as the root of the site.
+    ///
+    /// Changes are collected until none have happened for `debounce`, so a burst of saves
+    /// results in a single deploy. Each changed file is then uploaded with
+    /// [`Neocities::upload_if_changed`], and `on_deploy` is called with the files that were
+    /// uploaded or the error that stopped the deploy. Files deleted locally are not deleted
+    /// from the site. An error reported by the watcher for a single event is also passed to
+    /// `on_deploy`, and watching carries on.
+    ///
+    /// This only returns if the directory can't be watched, so stop it by dropping the future
+    pub async fn watch_and_deploy<P, F>(
+        &self,
+        root: P,
+        debounce: Duration,
+        mut on_deploy: F,
+    ) -> Result<(), NeocitiesError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Result<DeployReport, NeocitiesError>),
+    {
+        // Events use absolute paths, so `root` has to be as well to strip it from them
+        let root = std::fs::canonicalize(root)?;
+        let (sender, mut events) = mpsc::unbounded_channel();
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        while let Some(event) = events.recv().await {
+            let mut changed = BTreeSet::new();
+            collect(event, &mut changed, &mut on_deploy);
+
+            while let Ok(Some(event)) = timeout(debounce, events.recv()).await {
+                collect(event, &mut changed, &mut on_deploy);
+            }
+
+            on_deploy(self.upload_changed(&root, &changed).await);
+        }
+
+        Ok(())
+    }
+
+    // Upload every file in `changed` that differs from the site
+    async fn upload_changed(
+        &self,
+        root: &Path,
+        changed: &BTreeSet<PathBuf>,
+    ) -> Result<DeployReport, NeocitiesError> {
+        let mut report = DeployReport::default();
+
+        for path in changed.iter().filter(|path| path.is_file()) {
+            let remote = remote_path(root, path);
+
+            if self.upload_if_changed(remote.clone(), path).await? == UploadOutcome::Uploaded {
+                report.uploaded.push(remote);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+// Add the paths of a watcher event to `changed`, passing an error to `on_deploy` instead
+fn collect<F>(
+    event: notify::Result<notify::Event>,
+    changed: &mut BTreeSet<PathBuf>,
+    on_deploy: &mut F,
+) where
+    F: FnMut(Result<DeployReport, NeocitiesError>),
+{
+    match event {
+        Ok(event) => changed.extend(event.paths),
+        Err(error) => on_deploy(Err(error.into())),
+    }
+}
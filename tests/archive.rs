@@ -0,0 +1,158 @@
+// Backups and restores, which need the `archive` or `zip` feature
+#![cfg(any(feature = "archive", feature = "zip"))]
+
+mod common;
+
+use serde_json::json;
+use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+use common::{builder, directory, file, success};
+
+// A site with a directory, two files that download and one that doesn't
+async fn site() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("index.html", b"home"),
+                directory("images"),
+                file("images/cat.png", b"meow"),
+                file("gone.html", b"gone"),
+            ]),
+        ))
+        .mount(&server)
+        .await;
+    for (page, contents) in [("index.html", "home"), ("images/cat.png", "meow")] {
+        Mock::given(path(format!("/site/{}", page)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(contents))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(path("/site/gone.html"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+fn site_client(server: &MockServer) -> neocities::Neocities {
+    builder(server)
+        .site_url(format!("{}/site", server.uri()))
+        .build()
+        .unwrap()
+}
+
+#[cfg(feature = "archive")]
+#[tokio::test]
+async fn backup_to_archive_writes_every_file_and_reports_failures() {
+    use std::io::Read;
+
+    let server = site().await;
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("backup.tar.gz");
+
+    let report = site_client(&server).backup_to_archive(&dest).await.unwrap();
+
+    assert_eq!(report.exported, ["index.html", "images/cat.png"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, "gone.html");
+
+    let gzip = flate2::read::GzDecoder::new(std::fs::File::open(&dest).unwrap());
+    let mut archive = tar::Archive::new(gzip);
+    let mut entries = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        entries.push((entry.path().unwrap().display().to_string(), contents));
+    }
+    assert_eq!(
+        entries,
+        [
+            ("index.html".to_string(), "home".to_string()),
+            ("images/".to_string(), String::new()),
+            ("images/cat.png".to_string(), "meow".to_string()),
+        ]
+    );
+}
+
+#[cfg(feature = "zip")]
+#[tokio::test]
+async fn export_zip_writes_every_file_and_reports_failures() {
+    use std::io::{Cursor, Read};
+
+    let server = site().await;
+    let mut buffer = Cursor::new(Vec::new());
+
+    let report = site_client(&server).export_zip(&mut buffer).await.unwrap();
+
+    assert_eq!(report.exported, ["index.html", "images/cat.png"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, "gone.html");
+
+    let mut zip = zip::ZipArchive::new(buffer).unwrap();
+    let names: Vec<&str> = zip.file_names().collect();
+    assert_eq!(names.len(), 3);
+    let mut contents = String::new();
+    zip.by_name("images/cat.png")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "meow");
+    assert!(zip.by_name("images/").unwrap().is_dir());
+}
+
+// Add an entry named exactly `name` to `tar`, which the tar builder would otherwise normalize
+#[cfg(feature = "archive")]
+fn append_raw<W: std::io::Write>(
+    tar: &mut tar::Builder<W>,
+    name: &str,
+    entry_type: tar::EntryType,
+    contents: &[u8],
+) {
+    let mut header = tar::Header::new_gnu();
+    header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+    header.set_entry_type(entry_type);
+    header.set_mode(0o644);
+    header.set_size(contents.len() as u64);
+    header.set_cksum();
+    tar.append(&header, contents).unwrap();
+}
+
+#[cfg(feature = "archive")]
+#[tokio::test]
+async fn restore_from_archive_uploads_regular_files_under_normalized_paths() {
+    use tar::EntryType;
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("backup.tar.gz");
+    let gzip = flate2::write::GzEncoder::new(
+        std::fs::File::create(&archive).unwrap(),
+        flate2::Compression::default(),
+    );
+    let mut tar = tar::Builder::new(gzip);
+    append_raw(&mut tar, "./index.html", EntryType::Regular, b"home");
+    append_raw(&mut tar, "./images/", EntryType::Directory, b"");
+    append_raw(&mut tar, "./images/cat.png", EntryType::Regular, b"meow");
+    append_raw(&mut tar, "../evil.html", EntryType::Regular, b"evil");
+    append_raw(&mut tar, "./passwd", EntryType::Symlink, b"");
+    append_raw(&mut tar, "./copy.html", EntryType::Link, b"");
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let server = MockServer::start().await;
+    common::accept_uploads(&server).await;
+
+    let report = site_client(&server)
+        .restore_from_archive(&archive, false)
+        .await
+        .unwrap();
+
+    assert_eq!(report.restored, ["index.html", "images/cat.png"]);
+    assert_eq!(report.rejected, ["../evil.html", "./passwd", "./copy.html"]);
+    assert_eq!(
+        common::uploaded_names(&server).await,
+        ["index.html", "images/cat.png"]
+    );
+}
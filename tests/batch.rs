@@ -0,0 +1,154 @@
+mod common;
+
+use neocities::{Batch, BatchError, Neocities};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{accept_uploads, builder, directory, error, file, site, success, uploaded_parts};
+
+fn site_client(server: &MockServer) -> Neocities {
+    builder(server)
+        .site_url(format!("{}/site", server.uri()))
+        .build()
+        .unwrap()
+}
+
+// Serve `contents` as the public copy of `page`
+async fn public_file(server: &MockServer, page: &str, contents: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("/site/{}", page)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(contents))
+        .mount(server)
+        .await;
+}
+
+// Reject uploads of `page`, mounted before anything accepting uploads so it takes precedence
+async fn reject_upload(server: &MockServer, page: &str) {
+    Mock::given(path("/api/upload"))
+        .and(body_string_contains(format!("name=\"{}\"", page)))
+        .respond_with(error("invalid_file_type", "file type is not allowed"))
+        .mount(server)
+        .await;
+}
+
+async fn accept_deletes(server: &MockServer) {
+    Mock::given(path("/api/delete"))
+        .respond_with(success("message", json!("file(s) have been deleted")))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn failed_batch_restores_overwritten_and_deleted_files_and_removes_new_ones() {
+    let server = site(json!([file("a.html", b"old a"), file("c.html", b"c")])).await;
+    public_file(&server, "a.html", "old a").await;
+    public_file(&server, "c.html", "c").await;
+    reject_upload(&server, "d.exe").await;
+    accept_uploads(&server).await;
+    accept_deletes(&server).await;
+
+    let result = Batch::new()
+        .upload("a.html".to_string(), b"new a".to_vec())
+        .upload("b.html".to_string(), b"b".to_vec())
+        .delete("c.html".to_string())
+        .upload("d.exe".to_string(), b"d".to_vec())
+        .execute(&site_client(&server))
+        .await;
+
+    match result {
+        Err(BatchError::Operation {
+            step,
+            path,
+            rolled_back,
+            not_rolled_back,
+            ..
+        }) => {
+            assert_eq!(step, 3);
+            assert_eq!(path, "d.exe");
+            assert_eq!(rolled_back, ["c.html", "b.html", "a.html"]);
+            assert!(not_rolled_back.is_empty(), "{:?}", not_rolled_back);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let uploads: Vec<(String, Vec<u8>)> = uploaded_parts(&server)
+        .await
+        .into_iter()
+        .map(|part| (part.name, part.body))
+        .collect();
+    assert_eq!(
+        uploads[uploads.len() - 2..],
+        [
+            ("c.html".to_string(), b"c".to_vec()),
+            ("a.html".to_string(), b"old a".to_vec()),
+        ]
+    );
+    let deleted: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/api/delete")
+        .map(|request| request.url.query().unwrap_or_default().to_string())
+        .collect();
+    // `c.html` deleted by the batch, then `b.html` deleted by the rollback
+    assert_eq!(deleted.len(), 2);
+    assert!(deleted[1].contains("b.html"), "{:?}", deleted);
+}
+
+#[tokio::test]
+async fn deleted_paths_with_nothing_saved_are_not_rolled_back() {
+    let server = site(json!([directory("empty"), file("index.html", b"home")])).await;
+    reject_upload(&server, "bad.exe").await;
+    accept_deletes(&server).await;
+
+    let result = Batch::new()
+        .delete("empty".to_string())
+        .upload("bad.exe".to_string(), b"bad".to_vec())
+        .execute(&site_client(&server))
+        .await;
+
+    match result {
+        Err(BatchError::Operation {
+            rolled_back,
+            not_rolled_back,
+            ..
+        }) => {
+            assert!(rolled_back.is_empty(), "{:?}", rolled_back);
+            assert_eq!(not_rolled_back.len(), 1);
+            assert_eq!(not_rolled_back[0].0, "empty");
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn listing_failure_is_reported_as_setup_without_sending_anything() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(error("invalid_auth", "invalid credentials"))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/upload"))
+        .respond_with(success("message", json!("uploaded")))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/delete"))
+        .and(query_param("filenames[]", "a.html"))
+        .respond_with(success("message", json!("deleted")))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let result = Batch::new()
+        .upload("b.html".to_string(), b"b".to_vec())
+        .delete("a.html".to_string())
+        .execute(&site_client(&server))
+        .await;
+
+    assert!(matches!(result, Err(BatchError::Setup(_))), "{:?}", result);
+}
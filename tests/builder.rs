@@ -0,0 +1,209 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use neocities::{NeocitiesBuilder, NeocitiesError};
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    redirect::Policy,
+    StatusCode,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{header, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{builder, client, file, info, success, KEY};
+
+// An API server redirecting `info` to `/moved` on another server, which answers it
+async fn redirected() -> (MockServer, MockServer) {
+    let api = MockServer::start().await;
+    let other = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(
+            ResponseTemplate::new(302).insert_header("location", format!("{}/moved", other.uri())),
+        )
+        .mount(&api)
+        .await;
+    Mock::given(path("/moved"))
+        .respond_with(success("info", info("test")))
+        .mount(&other)
+        .await;
+
+    (api, other)
+}
+
+#[tokio::test]
+async fn redirects_to_another_host_are_followed_without_credentials() {
+    let (api, other) = redirected().await;
+
+    let info = client(&api).info("").await.unwrap();
+
+    assert_eq!(info.site_name, "test");
+    let requests = other.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(!requests[0].headers.contains_key("authorization"));
+}
+
+#[tokio::test]
+async fn redirects_are_not_followed_with_policy_none() {
+    let (api, other) = redirected().await;
+    let client = builder(&api).redirect(Policy::none()).build().unwrap();
+
+    assert!(client.info("").await.is_err());
+    assert!(other.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn accept_language_is_sent_with_every_request_when_set() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .and(header("accept-language", "en-US"))
+        .respond_with(success("info", info("test")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/list"))
+        .and(header("accept-language", "en-US"))
+        .respond_with(success("files", json!([])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = builder(&server).accept_language("en-US").build().unwrap();
+    client.info("").await.unwrap();
+    client.list("").await.unwrap();
+}
+
+#[tokio::test]
+async fn accept_language_is_not_sent_by_default() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(success("info", info("test")))
+        .mount(&server)
+        .await;
+
+    client(&server).info("").await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(!requests[0].headers.contains_key("accept-language"));
+}
+
+#[test]
+fn an_invalid_accept_language_fails_the_build() {
+    let result = NeocitiesBuilder::key("key".to_string())
+        .accept_language("en\nX-Injected: 1")
+        .build();
+
+    assert!(
+        matches!(result, Err(NeocitiesError::InvalidConfig(_))),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[tokio::test]
+async fn responses_over_the_size_limit_are_rejected() {
+    let server = MockServer::start().await;
+    let files: Vec<_> = (0..100)
+        .map(|i| file(&format!("page{}.html", i), b"page"))
+        .collect();
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", json!(files)))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/info"))
+        .respond_with(success("info", info("test")))
+        .mount(&server)
+        .await;
+
+    let client = builder(&server).max_response_bytes(1024).build().unwrap();
+
+    let err = client.list("").await.unwrap_err();
+    assert!(
+        matches!(err, NeocitiesError::ResponseTooLarge(1024)),
+        "{:?}",
+        err
+    );
+    // Smaller responses are still read
+    assert_eq!(client.info("").await.unwrap().site_name, "test");
+}
+
+#[tokio::test]
+async fn before_send_can_add_headers() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .and(header("x-request-id", "42"))
+        .respond_with(success("info", info("test")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = builder(&server)
+        .before_send(|request| {
+            request
+                .headers_mut()
+                .insert("x-request-id", HeaderValue::from_static("42"));
+        })
+        .build()
+        .unwrap();
+
+    client.info("").await.unwrap();
+}
+
+#[tokio::test]
+async fn before_send_cannot_change_the_credentials() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .and(header("authorization", format!("Bearer {}", KEY).as_str()))
+        .respond_with(success("info", info("test")))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let removing = builder(&server)
+        .before_send(|request| {
+            request.headers_mut().remove(AUTHORIZATION);
+        })
+        .build()
+        .unwrap();
+    let replacing = builder(&server)
+        .before_send(|request| {
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_static("Bearer stolen"));
+        })
+        .build()
+        .unwrap();
+
+    removing.info("").await.unwrap();
+    replacing.info("").await.unwrap();
+}
+
+#[tokio::test]
+async fn after_receive_sees_every_response_status() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(success("info", info("test")))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/list"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&statuses);
+
+    let client = builder(&server)
+        .after_receive(move |response| seen.lock().unwrap().push(response.status()))
+        .build()
+        .unwrap();
+    client.info("").await.unwrap();
+    assert!(client.list("").await.is_err());
+
+    assert_eq!(
+        *statuses.lock().unwrap(),
+        [StatusCode::OK, StatusCode::INTERNAL_SERVER_ERROR]
+    );
+}
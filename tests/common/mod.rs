@@ -0,0 +1,200 @@
+// Helpers shared by the tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use std::{fs, path::Path};
+
+use neocities::{Neocities, NeocitiesBuilder};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const KEY: &str = "test-key";
+
+pub const TIMESTAMP: &str = "Sat, 13 Jan 2024 17:49:20 -0000";
+
+// A builder for a client sending every API request to `server`
+pub fn builder(server: &MockServer) -> NeocitiesBuilder {
+    NeocitiesBuilder::key(KEY.to_string()).base_url(format!("{}/api", server.uri()))
+}
+
+pub fn client(server: &MockServer) -> Neocities {
+    builder(server).build().unwrap()
+}
+
+// A server whose `list` returns `files`, for tests that only need a listing
+pub async fn site(files: Value) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", files))
+        .mount(&server)
+        .await;
+    server
+}
+
+// A successful API response with `data` in `field`, such as `files` for a listing
+pub fn success(field: &str, data: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "result": "success", field: data }))
+}
+
+pub fn error(error_type: &str, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "result": "error",
+        "error_type": error_type,
+        "message": message,
+    }))
+}
+
+pub fn info(site_name: &str) -> Value {
+    json!({
+        "sitename": site_name,
+        "hits": 10,
+        "views": 5,
+        "created_at": TIMESTAMP,
+        "last_updated": TIMESTAMP,
+        "domain": null,
+        "tags": [],
+    })
+}
+
+pub fn file(path: &str, contents: &[u8]) -> Value {
+    json!({
+        "path": path,
+        "is_directory": false,
+        "size": contents.len(),
+        "updated_at": TIMESTAMP,
+        "sha1_hash": sha1_hex(contents),
+    })
+}
+
+pub fn directory(path: &str) -> Value {
+    json!({
+        "path": path,
+        "is_directory": true,
+        "updated_at": TIMESTAMP,
+    })
+}
+
+pub fn sha1_hex(contents: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(contents))
+}
+
+// A local directory containing `files`, each a path relative to it and its contents
+pub fn local_site(files: &[(&str, &str)]) -> TempDir {
+    let root = tempfile::tempdir().unwrap();
+
+    for (path, contents) in files {
+        write(&root.path().join(path), contents);
+    }
+
+    root
+}
+
+// Write `contents` to `path`, creating its parent directories
+pub fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+// A file sent to the mock server's upload endpoint
+#[derive(Debug)]
+pub struct UploadedPart {
+    pub name: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+// Accept every upload sent to `server`
+pub async fn accept_uploads(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/api/upload"))
+        .respond_with(success(
+            "message",
+            "your file(s) have been successfully uploaded".into(),
+        ))
+        .mount(server)
+        .await;
+}
+
+// Every file uploaded to `server` so far, in the order they were sent
+pub async fn uploaded_parts(server: &MockServer) -> Vec<UploadedPart> {
+    let mut parts = Vec::new();
+
+    for request in server.received_requests().await.unwrap_or_default() {
+        if request.url.path() != "/api/upload" {
+            continue;
+        }
+
+        let content_type = request.headers["content-type"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let boundary = format!("--{}", content_type.split("boundary=").nth(1).unwrap());
+
+        for raw in split(&request.body, boundary.as_bytes())
+            .into_iter()
+            .skip(1)
+        {
+            // The closing boundary is followed by `--`
+            if raw.starts_with(b"--") {
+                break;
+            }
+
+            let raw = &raw[2..raw.len() - 2];
+            let header_end = find(raw, b"\r\n\r\n").unwrap();
+            let headers = String::from_utf8_lossy(&raw[..header_end]).into_owned();
+            let name = headers
+                .split("name=\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .unwrap()
+                .to_string();
+            let content_type = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-type: "))
+                .or_else(|| {
+                    headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Type: "))
+                })
+                .map(str::to_string);
+
+            parts.push(UploadedPart {
+                name,
+                content_type,
+                body: raw[header_end + 4..].to_vec(),
+            });
+        }
+    }
+
+    parts
+}
+
+pub async fn uploaded_names(server: &MockServer) -> Vec<String> {
+    uploaded_parts(server)
+        .await
+        .into_iter()
+        .map(|part| part.name)
+        .collect()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn split<'a>(mut haystack: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+
+    while let Some(index) = find(haystack, separator) {
+        pieces.push(&haystack[..index]);
+        haystack = &haystack[index + separator.len()..];
+    }
+    pieces.push(haystack);
+
+    pieces
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use neocities::{NeocitiesBuilder, NeocitiesError};
+use wiremock::{matchers::path, Mock, MockServer};
+
+use common::{builder, error, info, success, KEY};
+
+#[tokio::test]
+async fn check_config_rejects_a_malformed_base_url_without_sending_anything() {
+    let client = NeocitiesBuilder::key(KEY.to_string())
+        .base_url("not a url")
+        .build()
+        .unwrap();
+
+    let err = client.check_config().await.unwrap_err();
+
+    assert!(matches!(err, NeocitiesError::InvalidConfig(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn check_config_rejects_a_site_url_that_is_not_http() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(success("info", info("test")))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let client = builder(&server)
+        .site_url("ftp://example.com")
+        .build()
+        .unwrap();
+
+    let err = client.check_config().await.unwrap_err();
+
+    assert!(matches!(err, NeocitiesError::InvalidConfig(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn check_config_reports_bad_credentials_as_an_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(error("invalid_auth", "invalid credentials"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let err = builder(&server)
+        .build()
+        .unwrap()
+        .check_config()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, NeocitiesError::ApiErr(..)), "{:?}", err);
+}
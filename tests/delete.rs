@@ -0,0 +1,198 @@
+mod common;
+
+use neocities::{ApiErrorKind, IndexHtml};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string, method, path, query_param},
+    Mock, MockServer,
+};
+
+use common::{accept_uploads, client, directory, error, file, success, uploaded_parts};
+
+async fn mock_delete(server: &MockServer, file: &str, response: wiremock::ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/api/delete"))
+        .and(query_param("filenames[]", file))
+        .respond_with(response)
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn delete_detailed_reports_each_path_in_a_mixed_batch() {
+    let server = MockServer::start().await;
+    let deleted = || success("message", "file(s) have been deleted".into());
+    mock_delete(&server, "a.html", deleted()).await;
+    mock_delete(
+        &server,
+        "gone.html",
+        error("missing_files", "gone.html was not found on your site"),
+    )
+    .await;
+    mock_delete(&server, "images", deleted()).await;
+
+    let paths = ["a.html", "gone.html", "images"].map(String::from);
+    let results = client(&server).delete_detailed(&paths).await;
+
+    let outcomes: Vec<(&str, Option<ApiErrorKind>)> = results
+        .iter()
+        .map(|(path, result)| {
+            (
+                path.as_str(),
+                result.as_ref().err().and_then(|e| e.api_error_kind()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ("a.html", None),
+            ("gone.html", Some(ApiErrorKind::MissingFiles)),
+            ("images", None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn delete_all_replaces_index_html_with_the_placeholder() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("index.html", b"old home page"),
+                file("about.html", b"about"),
+                directory("images"),
+                file("images/cat.png", b"meow"),
+            ]),
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/delete"))
+        .and(query_param("filenames[]", "index.html"))
+        .respond_with(error(
+            "cannot_delete_index",
+            "you cannot delete your index.html",
+        ))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/delete"))
+        .respond_with(success("message", "file(s) have been deleted".into()))
+        .mount(&server)
+        .await;
+    accept_uploads(&server).await;
+
+    let report = client(&server)
+        .delete_all(IndexHtml::Placeholder)
+        .await
+        .unwrap();
+
+    assert!(report.is_complete());
+    assert_eq!(report.deleted_files, ["about.html", "images/cat.png"]);
+    assert_eq!(report.deleted_dirs, ["images"]);
+
+    let uploads = uploaded_parts(&server).await;
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].name, "index.html");
+    let page = String::from_utf8(uploads[0].body.clone()).unwrap();
+    assert!(page.contains("<h1>Coming soon</h1>"), "{}", page);
+}
+
+#[tokio::test]
+async fn delete_all_keeps_the_report_when_index_html_cannot_be_replaced() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("index.html", b"old home page"),
+                file("about.html", b"about")
+            ]),
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/delete"))
+        .respond_with(success("message", "file(s) have been deleted".into()))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/upload"))
+        .respond_with(error("invalid_file_type", "index.html is not allowed"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let report = client(&server)
+        .delete_all(IndexHtml::Replace(b"new home page".to_vec()))
+        .await
+        .unwrap();
+
+    assert!(!report.is_complete());
+    assert_eq!(report.deleted_files, ["about.html"]);
+    assert_eq!(
+        report.index_error.and_then(|e| e.api_error_kind()),
+        Some(ApiErrorKind::InvalidFileType)
+    );
+}
+
+#[tokio::test]
+async fn delete_counts_match_the_listing() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .and(body_string("path=images"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("images/cat.png", b"meow"),
+                directory("images/dogs"),
+                file("images/dogs/rex.png", b"woof"),
+            ]),
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/list"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("index.html", b"home page"),
+                file("about.html", b"about"),
+                directory("images"),
+                file("images/cat.png", b"meow"),
+                directory("images/dogs"),
+                file("images/dogs/rex.png", b"woof"),
+            ]),
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/delete"))
+        .respond_with(success("message", "file(s) have been deleted".into()))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    assert_eq!(client.delete_all_count().await.unwrap(), 5);
+    assert_eq!(client.delete_dir_count("images/").await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn delete_rejects_invalid_paths_without_sending() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/delete"))
+        .respond_with(success("message", "file(s) have been deleted".into()))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    for paths in [vec!["about.html", "../secret"], vec!["/"]] {
+        let paths: Vec<String> = paths.into_iter().map(String::from).collect();
+        let error = client.delete(&paths).await.unwrap_err();
+        assert!(
+            matches!(error, neocities::NeocitiesError::InvalidInput(_)),
+            "{:?}",
+            paths
+        );
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::time::Duration;
+
+use neocities::NeocitiesError;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::builder;
+
+#[tokio::test]
+async fn download_uses_the_configured_site_url() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site/images/cat.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"meow".to_vec()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = builder(&server)
+        .site_url(format!("{}/site/", server.uri()))
+        .build()
+        .unwrap();
+
+    let file = client.download("/images/cat.png").await.unwrap();
+
+    assert_eq!(file, b"meow");
+}
+
+#[tokio::test]
+async fn download_with_progress_reports_bytes_against_the_content_length() {
+    let server = MockServer::start().await;
+    let contents = vec![7u8; 100_000];
+    Mock::given(path("/site/big.bin"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(contents.clone()))
+        .mount(&server)
+        .await;
+    let client = builder(&server)
+        .site_url(format!("{}/site", server.uri()))
+        .build()
+        .unwrap();
+
+    let mut updates = Vec::new();
+    let file = client
+        .download_with_progress("big.bin", |received, total| updates.push((received, total)))
+        .await
+        .unwrap();
+
+    assert_eq!(file, contents);
+    assert!(!updates.is_empty());
+    assert!(updates.iter().all(|(_, total)| *total == Some(100_000)));
+    assert!(updates.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(updates.last(), Some(&(100_000, Some(100_000))));
+}
+
+#[tokio::test]
+async fn await_site_live_polls_until_the_site_responds() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site/"))
+        .respond_with(ResponseTemplate::new(404))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/site/"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let client = builder(&server)
+        .site_url(format!("{}/site/", server.uri()))
+        .build()
+        .unwrap();
+
+    client
+        .await_site_live(Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn await_site_live_times_out_if_the_site_never_responds() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site/"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    let client = builder(&server)
+        .site_url(format!("{}/site/", server.uri()))
+        .build()
+        .unwrap();
+    let timeout = Duration::from_millis(200);
+
+    let result = client.await_site_live(timeout).await;
+
+    assert!(
+        matches!(result, Err(NeocitiesError::Timeout(t)) if t == timeout),
+        "{:?}",
+        result
+    );
+}
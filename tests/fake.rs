@@ -0,0 +1,86 @@
+#![cfg(feature = "test-util")]
+
+use neocities::{FakeNeocities, ListEntry, NeocitiesApi, NeocitiesError};
+
+// Deploy logic written against the trait, as a user of the crate would
+async fn publish<A: NeocitiesApi>(
+    api: &A,
+    pages: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<String>, NeocitiesError> {
+    let keep: Vec<String> = pages.iter().map(|(path, _)| path.clone()).collect();
+
+    for (path, contents) in pages {
+        api.upload(path, contents).await?;
+    }
+
+    let stale: Vec<String> = api
+        .list("")
+        .await?
+        .into_iter()
+        .filter_map(|entry| match entry {
+            ListEntry::File { path, .. } if path != "index.html" && !keep.contains(&path) => {
+                Some(path)
+            }
+            _ => None,
+        })
+        .collect();
+
+    if !stale.is_empty() {
+        api.delete(stale.clone()).await?;
+    }
+
+    Ok(stale)
+}
+
+#[tokio::test]
+async fn publish_uploads_pages_and_removes_stale_ones() {
+    let site = FakeNeocities::new("test").with_file("old.html", b"old".to_vec());
+
+    let removed = publish(
+        &site,
+        vec![
+            ("index.html".to_string(), b"home".to_vec()),
+            ("blog/post.html".to_string(), b"post".to_vec()),
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(removed, ["old.html"]);
+    let files = site.files();
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        ["blog/post.html", "index.html"]
+    );
+    assert_eq!(files["index.html"], b"home");
+    assert_eq!(
+        site.calls(),
+        [
+            "upload index.html",
+            "upload blog/post.html",
+            "list ",
+            "delete old.html"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fake_rejects_deleting_missing_files_like_the_server() {
+    let site = FakeNeocities::new("test");
+
+    let err = site
+        .delete(vec!["missing.html".to_string()])
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.api_error_kind(),
+        Some(neocities::ApiErrorKind::MissingFiles)
+    );
+    assert!(site
+        .list("")
+        .await
+        .unwrap()
+        .iter()
+        .any(|entry| entry.path() == "index.html"));
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use neocities::{ListEntry, NeocitiesError};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{client, directory, error, file, info, site, success};
+
+#[tokio::test]
+async fn directories_includes_every_level_of_a_deeply_nested_tree() {
+    let server = site(json!([
+        directory("a"),
+        file("a/b/c/d/e/page.html", b"deep"),
+        file("a/b/other.css", b"css"),
+        file("index.html", b"root"),
+    ]))
+    .await;
+
+    let directories = client(&server).directories("").await.unwrap();
+
+    assert_eq!(directories, ["a", "a/b", "a/b/c", "a/b/c/d", "a/b/c/d/e"]);
+}
+
+#[tokio::test]
+async fn list_map_looks_up_entries_by_path() {
+    let server = site(json!([
+        file("index.html", b"home"),
+        directory("images"),
+        file("images/cat.png", b"meow"),
+    ]))
+    .await;
+
+    let files = client(&server).list_map("").await.unwrap();
+
+    assert_eq!(files.len(), 3);
+    assert!(matches!(
+        files.get("images/cat.png"),
+        Some(ListEntry::File { size: 4, .. })
+    ));
+    assert!(matches!(
+        files.get("images"),
+        Some(ListEntry::Directory { .. })
+    ));
+    assert!(!files.contains_key("missing.html"));
+}
+
+#[tokio::test]
+async fn list_gives_the_same_listing_with_or_without_a_trailing_slash() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .and(body_string("path=images"))
+        .respond_with(success(
+            "files",
+            json!([
+                file("/images/cat.png", b"meow"),
+                directory("images/dogs/"),
+                file("images/dogs/rex.png", b"woof"),
+            ]),
+        ))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    let paths = |listing: Vec<ListEntry>| -> Vec<String> {
+        listing
+            .iter()
+            .map(|entry| entry.path().to_string())
+            .collect()
+    };
+    let without_slash = paths(client.list("images").await.unwrap());
+    let with_slash = paths(client.list("images/").await.unwrap());
+
+    assert_eq!(without_slash, with_slash);
+    assert_eq!(
+        without_slash,
+        ["images/cat.png", "images/dogs", "images/dogs/rex.png"]
+    );
+}
+
+#[tokio::test]
+async fn empty_sites_list_nothing_whatever_the_response_shape() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "result": "success" })))
+        .mount(&server)
+        .await;
+    assert!(client(&server).list("").await.unwrap().is_empty());
+
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", json!(null)))
+        .mount(&server)
+        .await;
+    assert!(client(&server).list("").await.unwrap().is_empty());
+
+    let server = site(json!([])).await;
+    assert!(client(&server).list("").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn directories_show_up_in_listing_output() {
+    let server = site(json!([
+        directory("images"),
+        file("images/cat.png", b"meow"),
+    ]))
+    .await;
+    let client = client(&server);
+
+    let printed: Vec<String> = client
+        .list("")
+        .await
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(printed, ["images/", "images/cat.png (4 bytes)"]);
+
+    assert_eq!(
+        client.paths("", false).await.unwrap(),
+        ["images", "images/cat.png"]
+    );
+    assert_eq!(client.paths("", true).await.unwrap(), ["images/cat.png"]);
+}
+
+#[tokio::test]
+async fn export_metadata_captures_the_info_and_listing() {
+    let server = site(json!([
+        directory("images"),
+        file("images/cat.png", b"meow")
+    ]))
+    .await;
+    Mock::given(path("/api/info"))
+        .respond_with(success("info", info("test")))
+        .mount(&server)
+        .await;
+
+    let snapshot = client(&server).export_metadata().await.unwrap();
+
+    assert_eq!(snapshot.info.site_name, "test");
+    let paths: Vec<&str> = snapshot.listing.iter().map(ListEntry::path).collect();
+    assert_eq!(paths, ["images", "images/cat.png"]);
+    assert!(chrono::DateTime::parse_from_rfc3339(&snapshot.captured_at).is_ok());
+}
+
+#[tokio::test]
+async fn export_metadata_fails_if_either_request_does() {
+    let server = site(json!([file("index.html", b"home")])).await;
+    Mock::given(path("/api/info"))
+        .respond_with(error("site_not_found", "could not find site"))
+        .mount(&server)
+        .await;
+
+    let result = client(&server).export_metadata().await;
+
+    assert!(
+        matches!(&result, Err(NeocitiesError::ApiErr(kind, _)) if kind == "site_not_found"),
+        "{:?}",
+        result.err()
+    );
+}
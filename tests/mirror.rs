@@ -0,0 +1,67 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use neocities::NeocitiesError;
+use serde_json::json;
+use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+use common::{builder, directory, file, site};
+
+// A site listing `files`, with `images/cat.png` available to download
+async fn mirrored_site(files: serde_json::Value) -> MockServer {
+    let server = site(files).await;
+    Mock::given(path("/site/images/cat.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"meow".to_vec()))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn since() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()
+}
+
+#[tokio::test]
+async fn mirror_incremental_downloads_updated_files_under_root() {
+    let server = mirrored_site(json!([
+        directory("images"),
+        file("/images/cat.png", b"meow")
+    ]))
+    .await;
+    let client = builder(&server)
+        .site_url(format!("{}/site", server.uri()))
+        .build()
+        .unwrap();
+    let root = tempfile::tempdir().unwrap();
+
+    let updated = client
+        .mirror_incremental(root.path(), since())
+        .await
+        .unwrap();
+
+    assert_eq!(updated, ["images/cat.png"]);
+    assert_eq!(
+        std::fs::read(root.path().join("images/cat.png")).unwrap(),
+        b"meow"
+    );
+}
+
+#[tokio::test]
+async fn mirror_incremental_rejects_paths_leading_outside_root() {
+    let server = mirrored_site(json!([file("images/../../escape.txt", b"gotcha")])).await;
+    let client = builder(&server)
+        .site_url(format!("{}/site", server.uri()))
+        .build()
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("mirror");
+
+    let err = client.mirror_incremental(&root, since()).await.unwrap_err();
+
+    assert!(
+        matches!(err, NeocitiesError::InvalidResponse(_)),
+        "{:?}",
+        err
+    );
+    assert!(!dir.path().join("escape.txt").exists());
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use neocities::{NeocitiesBuilder, NeocitiesError};
+use reqwest::Method;
+use serde_json::json;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{client, error, info, success, KEY};
+
+#[tokio::test]
+async fn raw_request_carries_the_api_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/some_new_endpoint"))
+        .and(header("authorization", format!("Bearer {}", KEY).as_str()))
+        .respond_with(success("info", info("test")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let response = client(&server)
+        .raw_request(Method::POST, "/some_new_endpoint")
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn raw_request_carries_the_login() {
+    let server = MockServer::start().await;
+    // `user:pass` in base64
+    Mock::given(path("/api/info"))
+        .and(header("authorization", "Basic dXNlcjpwYXNz"))
+        .respond_with(success("info", info("test")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = NeocitiesBuilder::login("user".to_string(), "pass".to_string())
+        .base_url(format!("{}/api/", server.uri()))
+        .build()
+        .unwrap();
+
+    let response = client
+        .raw_request(Method::GET, "info")
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn get_raw_keeps_every_field_of_the_response() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/info"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": "success",
+            "info": info("test"),
+            "brand_new_field": [1, 2, 3],
+        })))
+        .mount(&server)
+        .await;
+
+    let value = client(&server).get_raw("info?sitename=test").await.unwrap();
+
+    assert_eq!(value["result"], "success");
+    assert_eq!(value["info"]["sitename"], "test");
+    assert_eq!(value["info"]["hits"], 10);
+    assert_eq!(value["brand_new_field"], json!([1, 2, 3]));
+}
+
+#[tokio::test]
+async fn get_raw_returns_api_errors() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/info"))
+        .respond_with(error("site_not_found", "could not find site"))
+        .mount(&server)
+        .await;
+
+    let err = client(&server).get_raw("info").await.unwrap_err();
+
+    assert!(
+        matches!(&err, NeocitiesError::ApiErr(kind, _) if kind == "site_not_found"),
+        "{:?}",
+        err
+    );
+}
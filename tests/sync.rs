@@ -0,0 +1,414 @@
+mod common;
+
+use neocities::{
+    build_manifest, check_case_collisions, DeployState, LocalManifest, Neocities, NeocitiesError,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{
+    accept_uploads, builder, client, error, file, local_site, sha1_hex, site, success,
+    uploaded_names, uploaded_parts, write,
+};
+
+#[tokio::test]
+async fn local_manifest_hashes_each_file_only_once() {
+    let root = local_site(&[("index.html", "home"), ("about.html", "about")]);
+    let manifest = LocalManifest::build(root.path()).unwrap();
+    let server = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"about")
+    ]))
+    .await;
+
+    // Planning with the manifest reuses its hashes instead of reading the files again,
+    // so a change made after building it goes unnoticed
+    write(&root.path().join("about.html"), "changed");
+    let diff = client(&server).plan(&manifest).await.unwrap();
+
+    assert!(diff.is_empty(), "{:?}", diff);
+    assert!(!LocalManifest::build(root.path())
+        .unwrap()
+        .diff(&client(&server).list("").await.unwrap())
+        .is_empty());
+}
+
+#[tokio::test]
+async fn deploy_files_checks_every_batch_for_duplicates_before_uploading() {
+    let server = MockServer::start().await;
+    accept_uploads(&server).await;
+    let mut files: Vec<(String, Vec<u8>)> = (0..60)
+        .map(|i| (format!("page{}.html", i), b"page".to_vec()))
+        .collect();
+    // In a later batch than the file it duplicates
+    files.push(("/page0.html".to_string(), b"again".to_vec()));
+
+    let result = client(&server).deploy_files(files, false).await;
+
+    assert!(
+        matches!(&result, Err(NeocitiesError::InvalidInput(message)) if message.contains("`page0.html`")),
+        "{:?}",
+        result
+    );
+    assert!(uploaded_names(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn sync_delta_uploads_added_and_changed_files_and_deletes_removed_ones() {
+    let server = MockServer::start().await;
+    accept_uploads(&server).await;
+    Mock::given(path("/api/delete"))
+        .and(query_param("filenames[]", "drafts"))
+        .respond_with(success("message", json!("file(s) have been deleted")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", json!([])))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let old = local_site(&[
+        ("index.html", "home"),
+        ("about.html", "about"),
+        ("drafts/post.html", "draft"),
+    ]);
+    let new = local_site(&[
+        ("index.html", "home"),
+        ("about.html", "about, updated"),
+        ("post.html", "published"),
+    ]);
+
+    let report = client(&server)
+        .sync_delta(old.path(), new.path())
+        .await
+        .unwrap();
+
+    assert_eq!(report.uploaded, ["post.html", "about.html"]);
+    assert_eq!(report.deleted, ["drafts"]);
+    let mut sent = uploaded_names(&server).await;
+    sent.sort();
+    assert_eq!(sent, ["about.html", "post.html"]);
+}
+
+#[tokio::test]
+async fn apply_manifest_uploads_files_that_differ_from_the_site() {
+    let server = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"old about"),
+    ]))
+    .await;
+    accept_uploads(&server).await;
+    let root = local_site(&[
+        ("index.html", "home"),
+        ("about.html", "about"),
+        ("css/style.css", "body {}"),
+    ]);
+    let manifest = build_manifest(root.path()).unwrap();
+
+    let report = client(&server)
+        .apply_manifest(root.path(), &manifest)
+        .await
+        .unwrap();
+
+    assert_eq!(report.uploaded, ["css/style.css", "about.html"]);
+    assert!(report.deleted.is_empty());
+}
+
+#[tokio::test]
+async fn apply_manifest_rejects_paths_outside_the_directory() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", json!([])))
+        .expect(0)
+        .mount(&server)
+        .await;
+    accept_uploads(&server).await;
+    let parent = local_site(&[("secret.txt", "secret"), ("site/index.html", "home")]);
+    let root = parent.path().join("site");
+
+    for unsafe_path in ["../secret.txt", "./index.html", "/etc/passwd", ""] {
+        let mut manifest = build_manifest(&root).unwrap();
+        manifest
+            .entries
+            .push((unsafe_path.to_string(), sha1_hex(b"secret"), 6));
+
+        let result = client(&server).apply_manifest(&root, &manifest).await;
+
+        assert!(
+            matches!(&result, Err(NeocitiesError::InvalidInput(message)) if message.contains("manifest path")),
+            "{:?}",
+            result
+        );
+    }
+    assert!(uploaded_names(&server).await.is_empty());
+}
+
+#[test]
+fn case_collisions_are_grouped_by_lowercase_path() {
+    let root = local_site(&[
+        ("index.html", "home"),
+        ("Index.html", "home"),
+        ("images/cat.png", "meow"),
+        ("Images/dog.png", "woof"),
+        ("about.html", "about"),
+    ]);
+
+    let collisions = check_case_collisions(root.path()).unwrap();
+
+    let mut groups: Vec<Vec<String>> = collisions
+        .iter()
+        .map(|group| {
+            let mut names: Vec<String> = group
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(root.path()).unwrap();
+                    relative.to_string_lossy().replace('\\', "/")
+                })
+                .collect();
+            names.sort();
+            names
+        })
+        .collect();
+    groups.sort();
+    assert_eq!(groups, [["Images", "images"], ["Index.html", "index.html"]]);
+}
+
+#[test]
+fn no_case_collisions_is_empty() {
+    let root = local_site(&[("index.html", "home"), ("images/cat.png", "meow")]);
+
+    assert!(check_case_collisions(root.path()).unwrap().is_empty());
+}
+
+// A local site with a symbolic link to a file and one to a directory, both outside of it.
+// The returned directory holds the link targets and has to be kept alive
+#[cfg(unix)]
+fn site_with_symlinks() -> (tempfile::TempDir, tempfile::TempDir) {
+    let root = local_site(&[("index.html", "home")]);
+    let outside = local_site(&[
+        ("secret.txt", "secret file"),
+        ("shared/style.css", "body {}"),
+    ]);
+    std::os::unix::fs::symlink(
+        outside.path().join("secret.txt"),
+        root.path().join("link.txt"),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(outside.path().join("shared"), root.path().join("shared")).unwrap();
+
+    (root, outside)
+}
+
+#[cfg(unix)]
+#[test]
+fn local_manifest_skips_symlinks_by_default() {
+    let (root, _outside) = site_with_symlinks();
+
+    let manifest = LocalManifest::build(root.path()).unwrap();
+
+    assert_eq!(manifest.files().keys().collect::<Vec<_>>(), ["index.html"]);
+    assert!(manifest.directories().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn local_manifest_records_link_targets_when_following() {
+    let (root, _outside) = site_with_symlinks();
+
+    let manifest =
+        LocalManifest::build_with_symlinks(root.path(), neocities::SymlinkPolicy::Follow).unwrap();
+
+    assert_eq!(
+        manifest.files().keys().collect::<Vec<_>>(),
+        ["index.html", "link.txt", "shared/style.css"]
+    );
+    let link = &manifest.files()["link.txt"];
+    assert_eq!(link.size, 11);
+    assert_eq!(link.sha1_hash, sha1_hex(b"secret file"));
+    assert!(manifest.directories().contains("shared"));
+}
+
+#[cfg(unix)]
+#[test]
+fn local_manifest_fails_on_symlinks_with_the_error_policy() {
+    let (root, _outside) = site_with_symlinks();
+
+    let result = LocalManifest::build_with_symlinks(root.path(), neocities::SymlinkPolicy::Error);
+
+    assert!(
+        matches!(&result, Err(NeocitiesError::InvalidInput(message)) if message.contains("symbolic link")),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn compare_sites_of_identical_sites_is_empty() {
+    let files = || json!([file("index.html", b"home"), file("about.html", b"about")]);
+    let a = site(files()).await;
+    let b = site(files()).await;
+
+    let diff = Neocities::compare_sites(&client(&a), &client(&b))
+        .await
+        .unwrap();
+
+    assert!(diff.is_empty(), "{:?}", diff);
+}
+
+#[tokio::test]
+async fn compare_sites_reports_changes_from_a_to_b() {
+    let a = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"about"),
+        file("old.html", b"old"),
+    ]))
+    .await;
+    let b = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"about us"),
+        file("new.html", b"new"),
+    ]))
+    .await;
+
+    let diff = Neocities::compare_sites(&client(&a), &client(&b))
+        .await
+        .unwrap();
+
+    assert_eq!(diff.added, ["new.html"]);
+    assert_eq!(diff.removed, ["old.html"]);
+    assert_eq!(diff.modified, ["about.html"]);
+}
+
+#[tokio::test]
+async fn copy_site_skips_files_already_on_the_destination() {
+    let files = || json!([file("index.html", b"home"), file("about.html", b"about")]);
+    let from = site(files()).await;
+    let to = site(files()).await;
+    accept_uploads(&to).await;
+
+    let report = Neocities::copy_site(&client(&from), &client(&to), true)
+        .await
+        .unwrap();
+
+    assert!(report.uploaded.is_empty(), "{:?}", report);
+    assert!(report.deleted.is_empty(), "{:?}", report);
+    assert!(uploaded_names(&to).await.is_empty());
+}
+
+#[tokio::test]
+async fn copy_site_uploads_changed_files_and_prunes_stale_ones() {
+    let from = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"about us"),
+    ]))
+    .await;
+    Mock::given(path("/site/about.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"about us".to_vec()))
+        .mount(&from)
+        .await;
+    let to = site(json!([
+        file("index.html", b"home"),
+        file("about.html", b"about"),
+        file("old.html", b"old"),
+    ]))
+    .await;
+    accept_uploads(&to).await;
+    Mock::given(path("/api/delete"))
+        .and(query_param("filenames[]", "old.html"))
+        .respond_with(success("message", "file(s) have been deleted".into()))
+        .expect(1)
+        .mount(&to)
+        .await;
+    let from_client = builder(&from)
+        .site_url(format!("{}/site", from.uri()))
+        .build()
+        .unwrap();
+
+    let report = Neocities::copy_site(&from_client, &client(&to), true)
+        .await
+        .unwrap();
+
+    assert_eq!(report.uploaded, ["about.html"]);
+    assert_eq!(report.deleted, ["old.html"]);
+    let parts = uploaded_parts(&to).await;
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].name, "about.html");
+    assert_eq!(parts[0].body, b"about us");
+}
+
+// A local site with `index.html` and 60 other pages, enough for two upload batches
+fn many_pages() -> tempfile::TempDir {
+    let root = local_site(&[("index.html", "home")]);
+    for i in 0..60 {
+        write(&root.path().join(format!("page{}.html", i)), "page");
+    }
+    root
+}
+
+async fn requests_to(server: &MockServer, endpoint: &str) -> usize {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == endpoint)
+        .count()
+}
+
+#[tokio::test]
+async fn resume_deploy_lists_once_and_uploads_changed_files_in_batches() {
+    let root = many_pages();
+    let server = site(json!([file("index.html", b"home")])).await;
+    accept_uploads(&server).await;
+    let mut state = DeployState::default();
+
+    let report = client(&server)
+        .resume_deploy(root.path(), &mut state)
+        .await
+        .unwrap();
+
+    assert_eq!(report.uploaded.len(), 60);
+    assert!(!report.uploaded.contains(&"index.html".to_string()));
+    assert_eq!(state.uploaded.len(), 61);
+    assert_eq!(requests_to(&server, "/api/list").await, 1);
+    assert_eq!(requests_to(&server, "/api/upload").await, 2);
+}
+
+#[tokio::test]
+async fn resume_deploy_continues_after_the_last_uploaded_batch() {
+    let root = many_pages();
+    let server = site(json!([file("index.html", b"home")])).await;
+    Mock::given(path("/api/upload"))
+        .respond_with(success("message", "uploaded".into()))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(path("/api/upload"))
+        .respond_with(error("server_error", "try again later"))
+        .mount(&server)
+        .await;
+    let client = client(&server);
+    let mut state = DeployState::default();
+
+    assert!(client.resume_deploy(root.path(), &mut state).await.is_err());
+    // `index.html` was already on the site, plus the first batch
+    assert_eq!(state.uploaded.len(), 51);
+
+    server.reset().await;
+    Mock::given(path("/api/list"))
+        .respond_with(success("files", json!([file("index.html", b"home")])))
+        .mount(&server)
+        .await;
+    accept_uploads(&server).await;
+
+    let report = client.resume_deploy(root.path(), &mut state).await.unwrap();
+
+    assert_eq!(report.uploaded.len(), 10);
+    assert_eq!(state.uploaded.len(), 61);
+    assert_eq!(uploaded_names(&server).await.len(), 10);
+}
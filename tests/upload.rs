@@ -0,0 +1,68 @@
+mod common;
+
+use neocities::ApiErrorKind;
+use wiremock::{matchers::path, Mock, MockServer};
+
+use common::{accept_uploads, client, error, uploaded_names};
+
+fn files(files: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+    files
+        .iter()
+        .map(|(path, contents)| (path.to_string(), contents.as_bytes().to_vec()))
+        .collect()
+}
+
+#[tokio::test]
+async fn upload_many_with_report_groups_identical_files() {
+    let server = MockServer::start().await;
+    accept_uploads(&server).await;
+
+    let report = client(&server)
+        .upload_many_with_report(files(&[
+            ("/images/cat.png", "meow"),
+            ("about.html", "about"),
+            ("old/cat.png", "meow"),
+            ("index.html", "home"),
+            ("copy.html", "home"),
+        ]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report.message,
+        "your file(s) have been successfully uploaded"
+    );
+    let mut duplicates = report.duplicates;
+    duplicates.sort();
+    assert_eq!(
+        duplicates,
+        [
+            vec!["images/cat.png", "old/cat.png"],
+            vec!["index.html", "copy.html"],
+        ]
+    );
+    // Duplicates are still uploaded
+    assert_eq!(uploaded_names(&server).await.len(), 5);
+}
+
+#[tokio::test]
+async fn upload_many_with_report_returns_the_upload_error() {
+    let server = MockServer::start().await;
+    Mock::given(path("/api/upload"))
+        .respond_with(error(
+            "invalid_file_type",
+            "script.sh is not an allowed file type",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client(&server)
+        .upload_many_with_report(files(&[("script.sh", "echo"), ("copy.sh", "echo")]))
+        .await;
+
+    assert_eq!(
+        result.err().and_then(|e| e.api_error_kind()),
+        Some(ApiErrorKind::InvalidFileType)
+    );
+}
@@ -0,0 +1,375 @@
+mod common;
+
+use std::collections::HashMap;
+
+use neocities::{CancellationToken, Info, UploadDirOptions};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::{matchers::path, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use common::{accept_uploads, client, local_site, success, uploaded_names, uploaded_parts};
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    accept_uploads(&server).await;
+    server
+}
+
+#[tokio::test]
+async fn remote_prefix_is_joined_with_a_single_separator() {
+    let server = server().await;
+    let root = local_site(&[("post.html", "post"), ("css/style.css", "body {}")]);
+
+    let mut uploaded = client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new().remote_prefix("/blog/2024/"),
+        )
+        .await
+        .unwrap()
+        .uploaded;
+    uploaded.sort();
+
+    assert_eq!(uploaded, ["blog/2024/css/style.css", "blog/2024/post.html"]);
+    let mut sent = uploaded_names(&server).await;
+    sent.sort();
+    assert_eq!(sent, uploaded);
+}
+
+#[tokio::test]
+async fn no_remote_prefix_uploads_to_the_site_root() {
+    let server = server().await;
+    let root = local_site(&[("post.html", "post"), ("css/style.css", "body {}")]);
+
+    let mut uploaded = client(&server)
+        .upload_dir(root.path(), &UploadDirOptions::new().remote_prefix("/"))
+        .await
+        .unwrap()
+        .uploaded;
+    uploaded.sort();
+
+    assert_eq!(uploaded, ["css/style.css", "post.html"]);
+}
+
+#[tokio::test]
+async fn mime_type_overrides_are_sent_in_the_multipart_part() {
+    let server = server().await;
+    let root = local_site(&[
+        ("CNAME", "example.com"),
+        ("app/site.webmanifest", "{}"),
+        ("index.html", "home"),
+    ]);
+    let mime_types = HashMap::from([
+        ("CNAME".to_string(), "text/plain".to_string()),
+        (
+            "*.webmanifest".to_string(),
+            "application/manifest+json".to_string(),
+        ),
+    ]);
+
+    client(&server)
+        .upload_dir(root.path(), &UploadDirOptions::new().mime_types(mime_types))
+        .await
+        .unwrap();
+
+    let content_types: HashMap<String, Option<String>> = uploaded_parts(&server)
+        .await
+        .into_iter()
+        .map(|part| (part.name, part.content_type))
+        .collect();
+    assert_eq!(content_types["CNAME"].as_deref(), Some("text/plain"));
+    assert_eq!(
+        content_types["app/site.webmanifest"].as_deref(),
+        Some("application/manifest+json")
+    );
+    assert_ne!(content_types["index.html"].as_deref(), Some("text/plain"));
+}
+
+#[tokio::test]
+async fn generated_index_pages_go_in_directories_without_one() {
+    let server = server().await;
+    let root = local_site(&[
+        ("about.html", "about"),
+        ("blog/index.html", "blog"),
+        ("blog/post.html", "post"),
+        ("images/cat.png", "meow"),
+    ]);
+
+    let uploaded = client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new()
+                .remote_prefix("site")
+                .generate_index(),
+        )
+        .await
+        .unwrap()
+        .uploaded;
+
+    // Generated pages come after every real file
+    assert_eq!(
+        &uploaded[4..],
+        ["site/index.html", "site/images/index.html"]
+    );
+
+    let parts = uploaded_parts(&server).await;
+    let page = |name: &str| {
+        let part = parts.iter().find(|part| part.name == name).unwrap();
+        String::from_utf8(part.body.clone()).unwrap()
+    };
+    let root_index = page("site/index.html");
+    assert!(root_index.contains("<title>Index of /site</title>"));
+    assert!(root_index.contains("<a href=\"about.html\">about.html</a>"));
+    assert!(root_index.contains("<a href=\"blog/\">blog/</a>"));
+    assert!(root_index.contains("<a href=\"images/\">images/</a>"));
+    assert!(page("site/images/index.html").contains("<a href=\"cat.png\">cat.png</a>"));
+    assert_eq!(page("site/blog/index.html"), "blog");
+}
+
+// Accepts an upload and cancels the token, as if a stop button was pressed while it was sent
+struct CancelOnUpload(CancellationToken);
+
+impl Respond for CancelOnUpload {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        self.0.cancel();
+        success(
+            "message",
+            json!("your file(s) have been successfully uploaded"),
+        )
+    }
+}
+
+#[tokio::test]
+async fn cancelling_stops_further_uploads_and_reports_what_was_sent() {
+    let server = MockServer::start().await;
+    let cancel = CancellationToken::new();
+    Mock::given(path("/api/upload"))
+        .respond_with(CancelOnUpload(cancel.clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let root = local_site(&[("a.html", "a"), ("b.html", "b"), ("c.html", "c")]);
+
+    let report = client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new().generate_index().cancel_on(cancel),
+        )
+        .await
+        .unwrap();
+
+    assert!(report.cancelled);
+    assert_eq!(report.uploaded, uploaded_names(&server).await);
+    assert_eq!(report.uploaded.len(), 1);
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn compressed_path_collisions_are_found_before_uploading() {
+    let server = server().await;
+    let data = "[1, 2, 3],".repeat(4096);
+    let root = local_site(&[
+        ("a.txt", "uploaded first if anything is"),
+        ("data.json", &data),
+        ("data.json.gz", "already compressed"),
+    ]);
+
+    let result = client(&server)
+        .upload_dir(root.path(), &UploadDirOptions::new().compress_text(true))
+        .await;
+
+    assert!(
+        matches!(&result, Err(neocities::NeocitiesError::InvalidInput(message)) if message.contains("`data.json.gz`")),
+        "{:?}",
+        result
+    );
+    assert!(uploaded_names(&server).await.is_empty());
+}
+
+// A local site with a symbolic link to a file and one to a directory, both outside of it.
+// The returned directory holds the link targets and has to be kept alive
+#[cfg(unix)]
+fn site_with_symlinks() -> (TempDir, TempDir) {
+    let root = local_site(&[("index.html", "home")]);
+    let outside = local_site(&[("secret.txt", "secret"), ("shared/style.css", "body {}")]);
+    std::os::unix::fs::symlink(
+        outside.path().join("secret.txt"),
+        root.path().join("link.txt"),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(outside.path().join("shared"), root.path().join("shared")).unwrap();
+
+    (root, outside)
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_are_skipped_by_default() {
+    let server = server().await;
+    let (root, _outside) = site_with_symlinks();
+
+    let report = client(&server)
+        .upload_dir(root.path(), &UploadDirOptions::new())
+        .await
+        .unwrap();
+
+    assert_eq!(report.uploaded, ["index.html"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_are_uploaded_as_their_targets_when_followed() {
+    let server = server().await;
+    let (root, _outside) = site_with_symlinks();
+
+    let mut uploaded = client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new().symlinks(neocities::SymlinkPolicy::Follow),
+        )
+        .await
+        .unwrap()
+        .uploaded;
+    uploaded.sort();
+
+    assert_eq!(uploaded, ["index.html", "link.txt", "shared/style.css"]);
+    let parts = uploaded_parts(&server).await;
+    let link = parts.iter().find(|part| part.name == "link.txt").unwrap();
+    assert_eq!(link.body, b"secret");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_fail_the_upload_with_the_error_policy() {
+    let server = server().await;
+    let (root, _outside) = site_with_symlinks();
+
+    let result = client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new().symlinks(neocities::SymlinkPolicy::Error),
+        )
+        .await;
+
+    assert!(
+        matches!(&result, Err(neocities::NeocitiesError::InvalidInput(message)) if message.contains("symbolic link")),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn pages_last_uploads_index_pages_in_the_final_phase() {
+    let server = server().await;
+    let root = local_site(&[
+        ("index.html", "home"),
+        ("about.html", "about"),
+        ("blog/index.html", "blog"),
+        ("css/style.css", "body {}"),
+        ("images/cat.png", "meow"),
+    ]);
+
+    client(&server)
+        .upload_dir(root.path(), &UploadDirOptions::new().pages_last())
+        .await
+        .unwrap();
+
+    let sent = uploaded_names(&server).await;
+    let phase = |name: &str| sent.iter().position(|sent| sent == name).unwrap();
+    let assets = phase("css/style.css").max(phase("images/cat.png"));
+    assert!(assets < phase("about.html"), "{:?}", sent);
+    assert!(phase("about.html") < phase("index.html"), "{:?}", sent);
+    assert!(phase("about.html") < phase("blog/index.html"), "{:?}", sent);
+}
+
+#[test]
+fn disallowed_files_depends_on_supporter_status() {
+    let root = local_site(&[
+        ("index.html", "home"),
+        ("images/cat.PNG", "meow"),
+        ("tools/build.sh", "#!/bin/sh"),
+        ("README", "no extension"),
+    ]);
+    let client = neocities::Neocities::new("key".to_string());
+
+    let mut disallowed = client.disallowed_files(root.path(), false).unwrap();
+    disallowed.sort();
+    assert_eq!(
+        disallowed,
+        [
+            root.path().join("README"),
+            root.path().join("tools/build.sh")
+        ]
+    );
+
+    assert!(client
+        .disallowed_files(root.path(), true)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn can_upload_depends_on_supporter_status() {
+    assert!(Info::can_upload("images/cat.PNG", false));
+    assert!(!Info::can_upload("tools/build.sh", false));
+    assert!(!Info::can_upload("README", false));
+    assert!(Info::can_upload("tools/build.sh", true));
+    assert!(Info::can_upload("README", true));
+}
+
+#[tokio::test]
+async fn the_first_matching_mime_type_pattern_wins() {
+    let server = server().await;
+    let root = local_site(&[
+        ("feeds/posts.xml", "<rss />"),
+        ("sitemap.xml", "<urlset />"),
+    ]);
+
+    client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new().mime_types([
+                ("feeds/*.xml", "application/rss+xml"),
+                ("*.xml", "application/xml"),
+                ("feeds/*", "text/plain"),
+            ]),
+        )
+        .await
+        .unwrap();
+
+    let content_types: HashMap<String, Option<String>> = uploaded_parts(&server)
+        .await
+        .into_iter()
+        .map(|part| (part.name, part.content_type))
+        .collect();
+    assert_eq!(
+        content_types["feeds/posts.xml"].as_deref(),
+        Some("application/rss+xml")
+    );
+    assert_eq!(
+        content_types["sitemap.xml"].as_deref(),
+        Some("application/xml")
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn mime_types_match_compressed_files_by_their_original_path() {
+    let server = server().await;
+    let data = "[1, 2, 3],".repeat(4096);
+    let root = local_site(&[("data.json", &data)]);
+
+    client(&server)
+        .upload_dir(
+            root.path(),
+            &UploadDirOptions::new()
+                .compress_text(true)
+                .mime_types([("data.json", "application/json")]),
+        )
+        .await
+        .unwrap();
+
+    let parts = uploaded_parts(&server).await;
+    assert_eq!(parts[0].name, "data.json.gz");
+    assert_eq!(parts[0].content_type.as_deref(), Some("application/json"));
+}
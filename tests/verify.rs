@@ -0,0 +1,196 @@
+mod common;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use neocities::{NeocitiesError, PathMismatch, UploadOutcome};
+use serde_json::json;
+
+use common::{accept_uploads, client, directory, file, sha1_hex, site, uploaded_names};
+
+fn local_file(contents: &[u8]) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), contents).unwrap();
+    file
+}
+
+#[tokio::test]
+async fn verify_file_matches_identical_contents() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    let local = local_file(b"hello");
+
+    let matches = client(&server)
+        .verify_file("page.html", local.path())
+        .await
+        .unwrap();
+
+    assert!(matches);
+}
+
+#[tokio::test]
+async fn verify_file_detects_different_contents() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    let local = local_file(b"goodbye");
+
+    let matches = client(&server)
+        .verify_file("page.html", local.path())
+        .await
+        .unwrap();
+
+    assert!(!matches);
+}
+
+#[tokio::test]
+async fn verify_file_is_false_when_the_remote_file_is_missing() {
+    let server = site(json!([file("other.html", b"hello")])).await;
+    let local = local_file(b"hello");
+
+    let matches = client(&server)
+        .verify_file("page.html", local.path())
+        .await
+        .unwrap();
+
+    assert!(!matches);
+}
+
+#[tokio::test]
+async fn verify_tree_reports_only_the_mismatched_file() {
+    let server = site(json!([
+        file("index.html", b"home"),
+        directory("css"),
+        file("css/style.css", b"body {}"),
+    ]))
+    .await;
+    let root = tempfile::tempdir().unwrap();
+    std::fs::write(root.path().join("index.html"), b"home").unwrap();
+    std::fs::create_dir(root.path().join("css")).unwrap();
+    std::fs::write(root.path().join("css/style.css"), b"body { color: red }").unwrap();
+
+    let mismatches = client(&server).verify_tree(root.path()).await.unwrap();
+
+    assert_eq!(
+        mismatches,
+        [PathMismatch {
+            path: "css/style.css".to_string(),
+            local_sha1: Some(sha1_hex(b"body { color: red }")),
+            remote_sha1: Some(sha1_hex(b"body {}")),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn upload_verified_accepts_a_matching_hash() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    accept_uploads(&server).await;
+
+    client(&server)
+        .upload_verified("page.html".to_string(), b"hello".to_vec())
+        .await
+        .unwrap();
+
+    assert_eq!(uploaded_names(&server).await, ["page.html"]);
+}
+
+#[tokio::test]
+async fn upload_verified_reports_a_different_or_missing_file() {
+    let server = site(json!([file("page.html", b"stale")])).await;
+    accept_uploads(&server).await;
+    let client = client(&server);
+
+    let err = client
+        .upload_verified("page.html".to_string(), b"hello".to_vec())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            NeocitiesError::HashMismatch { path, expected, actual }
+                if path == "page.html"
+                    && *expected == sha1_hex(b"hello")
+                    && actual.as_deref() == Some(sha1_hex(b"stale").as_str())
+        ),
+        "{:?}",
+        err
+    );
+
+    let err = client
+        .upload_verified("missing.html".to_string(), b"hello".to_vec())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, NeocitiesError::HashMismatch { actual: None, .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn upload_if_changed_skips_a_file_with_the_same_hash() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    accept_uploads(&server).await;
+    let local = local_file(b"hello");
+
+    let outcome = client(&server)
+        .upload_if_changed("page.html".to_string(), local.path())
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, UploadOutcome::Unchanged);
+    assert!(uploaded_names(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn upload_if_changed_uploads_a_different_or_missing_file() {
+    let server = site(json!([file("page.html", b"stale")])).await;
+    accept_uploads(&server).await;
+    let local = local_file(b"hello");
+    let client = client(&server);
+
+    for path in ["page.html", "new.html"] {
+        let outcome = client
+            .upload_if_changed(path.to_string(), local.path())
+            .await
+            .unwrap();
+        assert_eq!(outcome, UploadOutcome::Uploaded, "{}", path);
+    }
+
+    assert_eq!(uploaded_names(&server).await, ["page.html", "new.html"]);
+}
+
+#[tokio::test]
+async fn upload_if_newer_skips_a_file_older_than_the_remote_copy() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    accept_uploads(&server).await;
+    let local = local_file(b"changed");
+    // Modified in 2000, long before the remote copy
+    local
+        .as_file()
+        .set_modified(UNIX_EPOCH + Duration::from_secs(946_684_800))
+        .unwrap();
+
+    let outcome = client(&server)
+        .upload_if_newer("page.html".to_string(), local.path(), Duration::ZERO)
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, UploadOutcome::Unchanged);
+    assert!(uploaded_names(&server).await.is_empty());
+}
+
+#[tokio::test]
+async fn upload_if_newer_uploads_a_newer_or_missing_file() {
+    let server = site(json!([file("page.html", b"hello")])).await;
+    accept_uploads(&server).await;
+    // Modified now, long after the remote copy
+    let local = local_file(b"changed");
+    let client = client(&server);
+
+    for path in ["page.html", "new.html"] {
+        let outcome = client
+            .upload_if_newer(path.to_string(), local.path(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(outcome, UploadOutcome::Uploaded, "{}", path);
+    }
+
+    assert_eq!(uploaded_names(&server).await, ["page.html", "new.html"]);
+}